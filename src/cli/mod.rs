@@ -0,0 +1,525 @@
+use clap::{value_parser, Arg};
+use qrcode::render::unicode;
+use qrcode::QrCode;
+use rendit::renditlib::{AbsolutePath, ArrayMerge, LineEndings};
+
+fn qrcode_string(value: &str) -> String {
+    let code = QrCode::new(value).unwrap();
+    code.render::<unicode::Dense1x2>()
+        .dark_color(unicode::Dense1x2::Dark)
+        .light_color(unicode::Dense1x2::Light)
+        .quiet_zone(true)
+        .build()
+}
+
+/// Parses a `--set` value of the form `key.path=value`. The value is kept as a string.
+fn parse_set(arg: &str) -> Result<(String, serde_json::Value), String> {
+    let (path, value) = arg
+        .split_once('=')
+        .ok_or_else(|| format!("Expected `key.path=value`, got `{arg}`"))?;
+    Ok((path.to_owned(), serde_json::Value::String(value.to_owned())))
+}
+
+/// Parses a `--set-json` value of the form `key.path=<json>`.
+fn parse_set_json(arg: &str) -> Result<(String, serde_json::Value), String> {
+    let (path, value) = arg
+        .split_once('=')
+        .ok_or_else(|| format!("Expected `key.path=<json>`, got `{arg}`"))?;
+    let value =
+        serde_json::from_str(value).map_err(|e| format!("Invalid JSON for `{path}`: {e}"))?;
+    Ok((path.to_owned(), value))
+}
+
+/// Replaces the `\n`, `\t`, `\0` and `\\` escapes of a command-line value.
+fn unescape(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    let mut chars = value.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match (c, chars.peek().copied()) {
+            ('\\', Some(escaped @ ('n' | 't' | '0' | '\\'))) => {
+                chars.next();
+                result.push(match escaped {
+                    'n' => '\n',
+                    't' => '\t',
+                    '0' => '\0',
+                    _ => '\\',
+                });
+            }
+            _ => result.push(c),
+        }
+    }
+    result
+}
+
+pub struct Args {
+    pub template_file: Option<AbsolutePath>,
+    pub context_files: Vec<AbsolutePath>,
+    pub merge_arrays: ArrayMerge,
+    pub env_prefix: Option<String>,
+    pub env_file: Option<AbsolutePath>,
+    /// `--set` and `--set-json` overrides, in command-line order.
+    pub overrides: Vec<(String, serde_json::Value)>,
+    pub output_file: Option<AbsolutePath>,
+    pub each: bool,
+    pub ndjson: bool,
+    pub separator: String,
+    pub ndjson_output: bool,
+    pub verbose: u8,
+    pub open: bool,
+    pub watch: Option<u64>,
+    pub stdout: bool,
+    pub stderr: bool,
+    pub engine: Option<String>,
+    pub engine_list: bool,
+    pub extension: Option<String>,
+    pub line_endings: LineEndings,
+    pub root: Option<AbsolutePath>,
+    pub include_dirs: Vec<AbsolutePath>,
+    pub strict: Option<bool>,
+    #[cfg(feature = "engine-subst")]
+    pub subst_env: bool,
+}
+
+impl Args {
+    pub fn parse() -> Self {
+        let arg_matches = {
+            let about = format!(
+                "{description}\n\n  Author: {author}\n  License: {license}\n  Repository: {repository}\n{qrcode}",
+                description = env!("CARGO_PKG_DESCRIPTION"),
+                author = env!("CARGO_PKG_AUTHORS"),
+                license = env!("CARGO_PKG_LICENSE"),
+                repository = env!("CARGO_PKG_REPOSITORY"),
+                qrcode = qrcode_string(env!("CARGO_PKG_REPOSITORY"))
+            );
+
+            let command = clap::Command::new(env!("CARGO_PKG_NAME"))
+            .version(env!("CARGO_PKG_VERSION"))
+            .about(about)
+            .arg(
+                Arg::new("template_file")
+                    .value_name("TEMPLATE FILE")
+                    .long_help(
+r#"The template file to render.
+    
+Unless using the `--context <CONTEXT FILE>` option, this requires either the 
+`<TEMPLATE NAME>.ctx.json` or the `default.ctx.json` context files to be present in the template's directory.
+
+[Example]
+
+.
+├── my_template.ctx.json
+└── my_template.html
+
+If `my_template.ctx.json` is missing, `default.ctx.json` will be loaded automatically from the template's directory.
+
+.
+├── default.ctx.json
+└── my_template.html
+
+If `default.ctx.json` is missing from the template directory, it will be loaded automatically from the current working directory, instead.
+
+Context files may also be YAML, TOML, JSON5, CSV or TSV, looked up in this order: `.ctx.json`, `.ctx.yaml`, `.ctx.yml`, `.ctx.toml`, `.ctx.json5`, `.ctx.csv`, `.ctx.tsv`.
+
+[Output]
+    
+Unless using the `--output <OUTPUT FILE>` option, providing `<TEMPLATE FILE>`, produces a `<TEMPLATE NAME>.rendered.<EXTENSION>` file by default.
+
+.
+├── my_template.html
+└── my_template.rendered.html
+                   
+By NOT providing `<TEMPLATE FILE>`, STDIN mode is activated. Template data stream will be expected to come from STDIN, printing the results to STDOUT instead of writing to file."#
+                )
+                    .value_parser(value_parser!(AbsolutePath))
+                    .display_order(1)
+            ).arg(
+                Arg::new("context_file")
+                    .value_name("CONTEXT FILE")
+                    .long_help(
+r#"Override default loading of the context file with the specified context file. The format is decided by the file extension: JSON (default), YAML, TOML, JSON5, CSV or TSV.
+CSV and TSV files are loaded as an array of rows, keyed by the header row, with numbers and booleans converted.
+A directory is loaded as a nested object, keyed by its own name, then by its subdirectory and file names without the extension: `data/users/alice.json` is `data.users.alice`.
+May be repeated, deep merging the files in order, so later files override earlier ones."#
+                )
+                    .short('c')
+                    .long("context")
+                    .action(clap::ArgAction::Append)
+                    .value_parser(value_parser!(AbsolutePath))
+                    .display_order(2)
+            ).arg(
+                Arg::new("merge_arrays")
+                    .value_name("POLICY")
+                    .long_help("How arrays are merged when `--context` is repeated: `replace` the earlier array, or `append` to it.")
+                    .long("merge-arrays")
+                    .value_parser(["replace", "append"])
+                    .default_value("replace")
+                    .display_order(2)
+            ).arg(
+                Arg::new("env_prefix")
+                    .value_name("PREFIX")
+                    .long_help(
+r#"Add the environment variables that start with the given prefix to the context, under `env`.
+The prefix is stripped and `__` separates nesting levels: `RENDIT_DB__HOST` is available as `env.DB.HOST`."#
+                )
+                    .long("env-prefix")
+                    .value_parser(value_parser!(String))
+                    .display_order(2)
+            ).arg(
+                Arg::new("env_file")
+                    .value_name("ENV FILE")
+                    .long_help(
+r#"Add the variables of a `.env` file to the context, under `env`, the same way as `--env-prefix`.
+With `--env-prefix`, only the matching variables are added, and the process environment takes precedence over the file."#
+                )
+                    .long("env-file")
+                    .value_parser(value_parser!(AbsolutePath))
+                    .display_order(2)
+            ).arg(
+                Arg::new("set")
+                    .value_name("KEY.PATH=VALUE")
+                    .long_help(
+r#"Set a string value in the context, on top of the context files. May be repeated.
+Intermediate objects are created as needed, and array items are set by index: `--set servers[0].host=localhost`.
+Without any context file, the context starts out empty."#
+                )
+                    .long("set")
+                    .action(clap::ArgAction::Append)
+                    .value_parser(parse_set)
+                    .display_order(2)
+            ).arg(
+                Arg::new("set_json")
+                    .value_name("KEY.PATH=JSON")
+                    .long_help(
+r#"Set a JSON value in the context, on top of the context files. May be repeated, and is applied in order along with `--set`.
+Example: `--set-json 'servers=[{"host": "localhost"}]'`"#
+                )
+                    .long("set-json")
+                    .action(clap::ArgAction::Append)
+                    .value_parser(parse_set_json)
+                    .display_order(2)
+            ).arg(
+                Arg::new("output_file")
+                    .value_name("OUTPUT FILE")
+                    .long_help("Override default output file path with the specified file path.")
+                    .short('o')
+                    .long("output")
+                    .value_parser(value_parser!(AbsolutePath))
+                    .display_order(3)
+            ).arg(
+                Arg::new("each")
+                    .long_help(
+r#"Render the template once per item of the context, which must be an array, such as the rows of a CSV file.
+Each item is the context of its own output file, named by the `--output` pattern, where `{{ key.path }}` is replaced by the item's value:
+`--context customers.csv --each --output 'letters/{{ id }}.html'`
+Without a template file or with `--stdout`, the results are printed instead."#
+                )
+                    .long("each")
+                    .action(clap::ArgAction::SetTrue)
+                    .conflicts_with("open")
+                    .display_order(3)
+            ).arg(
+                Arg::new("ndjson")
+                    .long_help(
+r#"Read newline-delimited JSON contexts from STDIN, and render the template file once per line, streaming the results to STDOUT.
+The template is parsed once. Each line is merged over the context files, if any were given.
+A line that fails to parse or render is reported to STDERR, and the stream goes on."#
+                )
+                    .long("ndjson")
+                    .action(clap::ArgAction::SetTrue)
+                    .requires("template_file")
+                    .conflicts_with_all(["each", "watch", "output_file", "open"])
+                    .display_order(3)
+            ).arg(
+                Arg::new("separator")
+                    .value_name("SEPARATOR")
+                    .long_help("Written after each result of `--ndjson`. Supports the `\\n`, `\\t` and `\\0` escapes.")
+                    .long("separator")
+                    .value_parser(value_parser!(String))
+                    .default_value("\\n")
+                    .requires("ndjson")
+                    .display_order(3)
+            ).arg(
+                Arg::new("ndjson_output")
+                    .long_help(
+r#"Write the results of `--ndjson` as NDJSON records: `{"output": "..."}`.
+Lines that fail are written as `{"line": 3, "error": "..."}`, so every input line has a record."#
+                )
+                    .long("ndjson-output")
+                    .action(clap::ArgAction::SetTrue)
+                    .requires("ndjson")
+                    .conflicts_with("separator")
+                    .display_order(3)
+            ).arg(
+                Arg::new("stdout")
+                    .long_help("Print rendered result to STDOUT.")
+                    .long("stdout")
+                    .action(clap::ArgAction::SetTrue)
+                    .display_order(4)
+            ).arg(
+                Arg::new("stderr")
+                    .long_help("Print rendered result to STDERR.")
+                    .long("stderr")
+                    .action(clap::ArgAction::SetTrue)
+                    .display_order(5)
+            ).arg(
+                Arg::new("open")
+                    .long_help("Open the rendered output file with a default software.")
+                    .long("open")
+                    .short('O')
+                    .action(clap::ArgAction::SetTrue)
+                    .display_order(6)
+            ).arg(
+                Arg::new("watch")
+                    .long_help("Constantly render changes in the template with the context file every 2 seconds by default.")
+                    .long("watch")
+                    .short('w')
+                    .value_name("[optional] SECONDS")
+                    .num_args(0..=1) // between 0 and 1 value occurrences
+                    .default_missing_value("2")
+                    .value_parser(value_parser!(u64))
+                    .display_order(7)
+            ).arg(
+                Arg::new("engine")
+                    .value_name("ENGINE NAME")
+                    .long_help(
+r#"Force rendering with the specified render engine.
+Use only when there is no magic comment or a template file extension available.
+Engines may be chained with `+`, e.g. `tera+mjml`.
+Use `auto-guess` to guess the engine out of the template's syntax
+when there is neither a magic comment nor a template file extension."#
+                )
+                    .long("engine")
+                    .short('e')
+                    .value_parser(value_parser!(String))
+                    .display_order(8)
+            ).arg(
+                Arg::new("engine_list")
+                    .long_help("Print supported engine list for the `--engine <ENGINE NAME>` option.")
+                    .long("engine-list")
+                    .action(clap::ArgAction::SetTrue)
+                    .display_order(9)
+            ).arg(
+                Arg::new("extension")
+                    .value_name("EXTENSION")
+                    .long_help("Force the underlying template engine to treat the template data as if it is a file of the given extension. This may affect escaping of special characters.")
+                    .long("extension")
+                    .short('x')
+                    .value_parser(value_parser!(String))
+                    .display_order(10)
+            ).arg(
+                Arg::new("line_endings")
+                    .value_name("STYLE")
+                    .long_help(
+r#"Normalize the line endings of the output to `lf` (`\n`) or `crlf` (`\r\n`).
+By default, the output keeps the line endings of the template as they are rendered."#
+                )
+                    .long("line-endings")
+                    .value_parser(["lf", "crlf", "preserve"])
+                    .default_value("preserve")
+                    .display_order(10)
+            ).arg(
+                Arg::new("strict")
+                    .long_help(
+r#"Fail on variables that are missing from the context, reporting the variable, in every engine.
+By default, each engine keeps its own behavior: Tera and Liquid fail,
+while Handlebars, Mustache and Subst render missing variables as empty."#
+                )
+                    .long("strict")
+                    .action(clap::ArgAction::SetTrue)
+                    .display_order(11)
+            ).arg(
+                Arg::new("no_strict")
+                    .long_help(
+r#"Render variables that are missing from the context as empty.
+Tera and Liquid always fail on missing variables, and reject this option."#
+                )
+                    .long("no-strict")
+                    .action(clap::ArgAction::SetTrue)
+                    .conflicts_with("strict")
+                    .display_order(12)
+            ).arg(
+                Arg::new("root")
+                    .value_name("DIR")
+                    .long_help(
+r#"Reject the template, the templates and partials it references, the context files and the output files
+if they resolve outside of the directory, after following symlinks.
+Use it to render templates from contributors that are not fully trusted."#
+                )
+                    .long("root")
+                    .value_parser(value_parser!(AbsolutePath))
+                    .display_order(12)
+            ).arg(
+                Arg::new("verbose")
+                    .long_help(
+r#"Set the level of verbosity.
+    
+`-v` sets logging level to INFO
+    
+`-vv` sets logging level to DEBUG
+    
+`-vvv` sets logging level to TRACE
+    
+WARNING: Effects CLI / STDOUT output.
+Use the `--output` switch if you wish to commit the rendered output to file.
+Use the `--stderr` switch to avoid including the logger messages in the final output."#
+                )
+                .long("verbose")
+                .short('v')
+                .action(clap::ArgAction::Count)
+                .value_parser(value_parser!(u8))
+                .display_order(15)
+            );
+
+            #[cfg(feature = "engine-subst")]
+            let command = command.arg(
+                Arg::new("subst_env")
+                    .long_help("Let the `subst` engine fall back to environment variables for variables that are missing from the context.")
+                    .long("subst-env")
+                    .action(clap::ArgAction::SetTrue)
+                    .display_order(13)
+            );
+
+            let command = command.arg(
+                Arg::new("include_dirs")
+                    .value_name("DIR")
+                    .long_help(
+r#"Look for the templates and partials that a template includes in the given directory,
+after the template's directory. May be repeated, searching the directories in order,
+so the template's directory takes precedence over the first `--include-dir`, and so on.
+Used by Tera, MiniJinja, Liquid, Handlebars and Mustache."#
+                )
+                    .long("include-dir")
+                    .short('I')
+                    .action(clap::ArgAction::Append)
+                    .value_parser(value_parser!(AbsolutePath))
+                    .display_order(14)
+            );
+
+            command.get_matches()
+        };
+
+        let err_msg = "Bad argument configuration";
+
+        Args {
+            template_file: arg_matches
+                .get_one::<AbsolutePath>("template_file")
+                .map(|v| v.to_owned()),
+
+            context_files: arg_matches
+                .get_many::<AbsolutePath>("context_file")
+                .map(|v| v.cloned().collect())
+                .unwrap_or_default(),
+
+            env_prefix: arg_matches
+                .get_one::<String>("env_prefix")
+                .map(|v| v.to_owned()),
+
+            env_file: arg_matches
+                .get_one::<AbsolutePath>("env_file")
+                .map(|v| v.to_owned()),
+
+            overrides: {
+                let mut overrides: Vec<(usize, (String, serde_json::Value))> = Vec::new();
+
+                for id in ["set", "set_json"] {
+                    if let (Some(indices), Some(values)) = (
+                        arg_matches.indices_of(id),
+                        arg_matches.get_many::<(String, serde_json::Value)>(id),
+                    ) {
+                        overrides.extend(indices.zip(values.cloned()));
+                    }
+                }
+                overrides.sort_by_key(|(index, _)| *index);
+                overrides.into_iter().map(|(_, o)| o).collect()
+            },
+
+            merge_arrays: arg_matches
+                .get_one::<String>("merge_arrays")
+                .expect(err_msg)
+                .as_str()
+                .into(),
+
+            output_file: arg_matches
+                .get_one::<AbsolutePath>("output_file")
+                .map(|v| v.to_owned()),
+
+            each: *arg_matches.get_one::<bool>("each").expect(err_msg),
+
+            ndjson: *arg_matches.get_one::<bool>("ndjson").expect(err_msg),
+
+            separator: unescape(arg_matches.get_one::<String>("separator").expect(err_msg)),
+
+            ndjson_output: *arg_matches.get_one::<bool>("ndjson_output").expect(err_msg),
+
+            verbose: *arg_matches.get_one::<u8>("verbose").expect(err_msg),
+
+            open: *arg_matches.get_one::<bool>("open").expect(err_msg),
+
+            watch: arg_matches.get_one::<u64>("watch").map(|v| v.to_owned()),
+
+            stdout: *arg_matches.get_one::<bool>("stdout").expect(err_msg),
+
+            stderr: *arg_matches.get_one::<bool>("stderr").expect(err_msg),
+
+            engine: arg_matches
+                .get_one::<String>("engine")
+                .map(|v| v.to_owned()),
+
+            engine_list: *arg_matches.get_one::<bool>("engine_list").expect(err_msg),
+
+            extension: arg_matches
+                .get_one::<String>("extension")
+                .map(|v| v.to_owned()),
+
+            line_endings: arg_matches
+                .get_one::<String>("line_endings")
+                .expect(err_msg)
+                .as_str()
+                .into(),
+
+            root: arg_matches
+                .get_one::<AbsolutePath>("root")
+                .map(|v| v.to_owned()),
+
+            include_dirs: arg_matches
+                .get_many::<AbsolutePath>("include_dirs")
+                .map(|v| v.cloned().collect())
+                .unwrap_or_default(),
+
+            strict: match (
+                *arg_matches.get_one::<bool>("strict").expect(err_msg),
+                *arg_matches.get_one::<bool>("no_strict").expect(err_msg),
+            ) {
+                (true, _) => Some(true),
+                (_, true) => Some(false),
+                _ => None,
+            },
+
+            #[cfg(feature = "engine-subst")]
+            subst_env: *arg_matches.get_one::<bool>("subst_env").expect(err_msg),
+        }
+    }
+}
+
+// impl<'arg_matches> Args<'arg_matches> {
+//     fn parse(arg_matches: &ArgMatches) -> Args {
+//         // let arg_matches = arg_matches();
+
+//         let err_msg = "Bad argument configuration";
+
+//         Args {
+//             template_file: arg_matches.get_one::<AbsolutePath>("template_file"),
+//             context_file: arg_matches.get_one::<AbsolutePath>("context_file"),
+//             output_file: arg_matches.get_one::<AbsolutePath>("output_file"),
+//             verbose: *arg_matches.get_one::<u8>("verbose").expect(err_msg),
+//             open: *arg_matches.get_one::<bool>("open").expect(err_msg),
+//             watch: arg_matches.get_one::<u64>("watch"),
+//             stdout: *arg_matches.get_one::<bool>("stdout").expect(err_msg),
+//             stderr: *arg_matches.get_one::<bool>("stderr").expect(err_msg),
+//             engine: arg_matches.get_one::<String>("engine"),
+//             engine_list: *arg_matches.get_one::<bool>("engine_list").expect(err_msg),
+//             extension: arg_matches.get_one::<String>("extension"),
+//         }
+//     }
+// }
@@ -1 +1,5 @@
+// Work in progress: application modes to separate the logical flows of the CLI.
+#[allow(unused)]
 mod app;
+
+pub mod renditlib;
@@ -1,35 +1,28 @@
-use anyhow::{anyhow, Context, Result};
-use clap::{value_parser, Arg};
-use handlebars::Handlebars;
-use path_slash::PathExt;
-// use human_panic::setup_panic;
-use enum_iterator::{all, Sequence};
+mod cli;
+
+use anyhow::{bail, Context, Result};
 use log::LevelFilter;
-use qrcode::render::unicode;
-use qrcode::QrCode;
-use regex::{Regex, RegexBuilder};
+use regex::{Captures, Regex};
+use rendit::renditlib::{
+    env_object, get_path, merge_values, prepare, read_env_file, read_env_vars, AbsolutePath,
+    ContextData, DetectionMethod, EngineRegistry, PreparedTemplate, RenderOptions, RenditError,
+    TemplateData, CONTEXT_EXTENSIONS,
+};
+use serde_json::Value;
 use simplelog::TermLogger;
 use std::{
-    borrow::{Borrow, Cow},
-    ffi::{OsStr, OsString},
     fs::{self, OpenOptions},
-    io::{BufWriter, Write},
-    ops::Deref,
+    io::{BufWriter, Read, Write},
     path::{Path, PathBuf},
-    process,
-    rc::Rc,
-    str::FromStr,
-    thread,
+    process, thread,
     time::Duration,
 };
-use tera::Tera;
 
-type Contents = Rc<String>;
-type EngineName = String;
+use cli::Args;
+#[cfg(feature = "engine-subst")]
+use rendit::renditlib::SubstRenderer;
 
 // TODO: 9.8.2022
-// TODO: Bonus: STDIN loop
-// TODO: Enable usage of external templates for `include` [Liquid](https://github.com/leftwm/leftwm/issues/439)
 // TODO: Logic for printing errors within `--watch`, should apply only when `.ctx.json` or template are changing. Maybe we should loop just when changes are detected while it checks for changes every 2 seconds by default.
 
 // TODO: 18.8.2022
@@ -39,531 +32,39 @@ type EngineName = String;
 // TODO: File Mode (what we have now, but without supporting STDIN) `--filemode` or `--mode file`
 // TODO: STDIN Mode (Read from STDIN) `--pipe mode`, `--pipe` or `--mode pipe`, `--stdin`, `--mode stdin`
 
-fn qrcode_string(value: &str) -> String {
-    let code = QrCode::new(value).unwrap();
-    let image = code
-        .render::<unicode::Dense1x2>()
-        .dark_color(unicode::Dense1x2::Dark)
-        .light_color(unicode::Dense1x2::Light)
-        .quiet_zone(true)
-        .build();
-    image
-}
-
 // qrcode!(env!("CARGO_PKG_REPOSITORY"))
 const DEFAULT_CONTEXT_FILE: &str = "default.ctx.json";
+const DEFAULT_CONTEXT_NAME: &str = "default";
 
-/// Scan the template for reference to other templates, such as:
-/// `{% include %}`, `{% extend %}` or `{% import %}` calls
-#[inline]
-fn find_template_references<P: AsRef<Path>>(content: &str, cwd: Option<P>) -> Vec<AbsolutePath> {
-    let re = Regex::new(
-        r#"\{%\s+?(?:import|include|extend)\s+?"(?P<template>[a-zA-Z0-9.\-/\\_]+?)"\s.*?%\}"#,
-    )
-    .expect("Bad regex pattern.");
-
-    let mut buf: Vec<AbsolutePath> = Vec::new();
-
-    log::debug!("Scanning for template references...");
-
-    for cap in re.captures_iter(content) {
-        log::debug!("Detected reference: \"{}\"", &cap["template"]);
-        // TODO: Make path relative to main template
-        let path = if let Some(p) = &cwd {
-            p.as_ref().with_file_name(&cap["template"]).into()
-        } else {
-            cap["template"].into()
-        };
-
-        buf.push(path);
-    }
-    buf
-}
-
-// A simple implementation of `% touch path` (ignores existing files)
-// Inspired by: https://doc.rust-lang.org/rust-by-example/std_misc/fs.html
-fn touch<P: AsRef<Path>>(path: P) -> Result<()> {
-    OpenOptions::new().create(true).write(true).open(path)?;
-    Ok(())
-}
-
-// This function attempts to be ignorant about any problems.
-// It just tries to figure out if a given file path location.
-// If the path doesn't exists, it assumes someone else will scream about it.
-// On failure, it just returns the original Path.
-#[inline]
-fn new_canonicalize_path_buf<P: AsRef<Path>>(path: P) -> PathBuf {
-    // Canonicalize seem to be having trouble on Windows with relative paths that include a backslash.
-    // This work around is meant to make sure that before Canonicalize encounters the given path,
-    // its backslashes will be replaced with regular ones so `canonicalize` will be able to handle it.
-    let path: PathBuf = if path.as_ref().has_root() {
-        path.as_ref().into()
-    } else {
-        (&*path.as_ref().to_slash_lossy()).into()
-    };
-
-    match fs::canonicalize(&path) {
-        Ok(abs_path) => abs_path,
-        // On failure of getting the full path, keep the relative path.
-        //
-        // Possible failures of `fs::canonicalize`:
-        //  1. path does not exist.
-        //  2. A non-final component in path is not a directory.
-        Err(e) => match e.kind() {
-            std::io::ErrorKind::NotFound => match touch(&path) {
-                Ok(_) => {
-                    let res = new_canonicalize_path_buf(&path);
-                    match fs::remove_file(&res) {
-                        Ok(_) => {
-                            log::debug!(
-                                "canonicalize(): Removed touched file: \"{}\"",
-                                res.to_string_lossy()
-                            )
-                        }
-                        Err(_) => {
-                            log::error!(
-                                "canonicalize(): Unable to remove file after touch: \"{}\"",
-                                res.to_string_lossy()
-                            )
-                        }
-                    };
-                    res
-                }
-                Err(_) => path,
-            },
-            _ => path,
-        },
-    }
-}
-
-// Has the potential to be more correct. For the alpha and beta stages, I'll keep this function around.
-// #[inline]
-// fn new_full_path_buf<P: AsRef<Path>>(path: P) -> Result<PathBuf> {
-//     if path.as_ref().has_root() {
-//         Ok(path.as_ref().to_owned())
-//     } else {
-//         let exe_dir = current_exe()
-//             .context("Unable to get current executable file location")?
-//             .parent()
-//             .context("Unable to get current executable directory")?
-//             .to_owned();
-
-//         Ok(exe_dir.join(path))
-//     }
-// }
-
-// TODO: Move to an external crate, improve and with some more ideas and publish on crates.io.
-// TODO: `AbsolutePath` features should be implemented on `PathBuf` directly with proper traits, to avoid duplicating and interswitching between the types, making it seamless.
-// Old Note: Should behave just like a `PathBuf` and therefore should have the same methods + New security features (Restrict trait?)
-#[derive(Clone, Debug)]
-struct AbsolutePath {
-    path: PathBuf,
-}
-
-impl AbsolutePath {
-    #[inline]
-    fn into_inner(self) -> PathBuf {
-        self.path
-    }
-}
-
-impl AsRef<Path> for AbsolutePath {
-    #[inline]
-    fn as_ref(&self) -> &Path {
-        self.path.as_ref()
-    }
-}
-
-impl<T: ?Sized + AsRef<OsStr>> From<&T> for AbsolutePath {
-    /// Converts a borrowed [`OsStr`] to a [`AbsolutePath`].
-    ///
-    /// Allocates a [`AbsolutePath`] and copies the data into it.
-    #[inline]
-    fn from(s: &T) -> AbsolutePath {
-        AbsolutePath {
-            // path: new_full_path_buf(s.as_ref()).unwrap_or_else(|_| s.into()),
-            path: new_canonicalize_path_buf(s.as_ref()),
-        }
-    }
-}
-
-impl From<OsString> for AbsolutePath {
-    #[inline]
-    fn from(s: OsString) -> Self {
-        AbsolutePath {
-            // path: new_full_path_buf(&s).unwrap_or_else(|_| s.into()),
-            path: new_canonicalize_path_buf(s),
-        }
-    }
-}
-
-impl From<PathBuf> for AbsolutePath {
-    #[inline]
-    fn from(s: PathBuf) -> Self {
-        AbsolutePath {
-            // path: new_full_path_buf(&s).unwrap_or(s),
-            path: new_canonicalize_path_buf(s),
-        }
-    }
-}
-
-impl FromStr for AbsolutePath {
-    type Err = anyhow::Error;
-
-    #[inline]
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let res = AbsolutePath {
-            // path: new_full_path_buf(s).unwrap_or_else(|_| s.into()),
-            path: new_canonicalize_path_buf(s),
-        };
-        Ok(res)
-    }
-}
-
-impl std::fmt::Display for AbsolutePath {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.path.to_string_lossy())
-    }
-}
-
-// impl AsRef<Path> for &AbsolutePath {
-//     #[inline]
-//     fn as_ref(&self) -> &Path {
-//         self.path.as_ref()
-//     }
-// }
-
-impl AsRef<PathBuf> for AbsolutePath {
-    #[inline]
-    fn as_ref(&self) -> &PathBuf {
-        &self.path
-    }
-}
-
-impl AsRef<OsStr> for AbsolutePath {
-    #[inline]
-    fn as_ref(&self) -> &OsStr {
-        self.path.as_ref()
-    }
-}
-
-impl Borrow<Path> for AbsolutePath {
-    #[inline]
-    fn borrow(&self) -> &Path {
-        self.path.borrow()
-    }
-}
-
-impl Deref for AbsolutePath {
-    type Target = Path;
-
-    #[inline]
-    fn deref(&self) -> &Path {
-        self.path.deref()
-    }
-}
-
-/// Supported template engines
-#[non_exhaustive]
-#[derive(Clone, Copy, Debug, PartialEq, Sequence, strum_macros::Display)]
-enum TemplateEngine {
-    Tera,
-    Liquid,
-    Handlebars,
-    None,
+/// Returns the first existing context file next to `base`, trying `<base>.ctx.json`, `<base>.ctx.yaml` and so on.
+fn find_context_file<P: AsRef<Path>>(base: P) -> Option<PathBuf> {
+    CONTEXT_EXTENSIONS
+        .iter()
+        .map(|ext| base.as_ref().with_extension(ext))
+        .find(|path| path.exists())
 }
 
-impl FromStr for TemplateEngine {
-    type Err = anyhow::Error;
+/// Fails if the context file, or any file of a context directory, resolves outside of `root`.
+fn restrict_context(path: &AbsolutePath, root: &AbsolutePath) -> Result<(), RenditError> {
+    path.restrict_to(root)?;
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let res = match s.to_lowercase().as_str() {
-            "tera" => TemplateEngine::Tera,
-            "liquid" | "liq" => TemplateEngine::Liquid,
-            "handlebars" | "hbs" => TemplateEngine::Handlebars,
-            "none" => TemplateEngine::None,
-            _ => {
-                return Err(anyhow!(
-                    "Please try one of the supported engines in `--engine-list`"
-                ))
-            }
-        };
-        Ok(res)
+    if !path.is_dir() {
+        return Ok(());
     }
-}
 
-// impl FromStr for TemplateEngine {
-//     type Err = RenditError;
-
-//     fn from_str(s: &str) -> Result<Self, Self::Err> {
-//         let res = match s.to_lowercase().as_str() {
-//             "tera" => TemplateEngine::Tera,
-//             "liquid" | "liq" => TemplateEngine::Liquid,
-//             "handlebars" | "hbs" => TemplateEngine::Handlebars,
-//             "none" => TemplateEngine::None,
-//             _ => return Err(RenditError::UnknownEngine(s.to_owned())),
-//         };
-//         Ok(res)
-//     }
-// }
-
-pub fn rendered_path<P: AsRef<Path>>(input_path: P) -> PathBuf {
-    let file_extension = input_path.as_ref().extension();
-
-    match file_extension {
-        Some(os_path_ext) => {
-            let path_ext = os_path_ext.to_string_lossy().to_lowercase();
-
-            if path_ext != "none" && path_ext.parse::<TemplateEngine>().is_ok() {
-                input_path.as_ref().with_extension("")
-            } else {
-                let new_ext = format!("rendered.{path_ext}");
-                input_path.as_ref().with_extension(new_ext)
+    for entry in fs::read_dir(path).into_iter().flatten().flatten() {
+        match entry.file_type() {
+            // Symlinked directories are skipped when loading, as opposed to symlinked files.
+            Ok(file_type) if file_type.is_dir() => restrict_context(&entry.path().into(), root)?,
+            _ if entry.path().is_file() => {
+                AbsolutePath::from(entry.path()).restrict_to(root)?;
             }
-        }
-        None => input_path.as_ref().with_extension(String::from("rendered")),
-    }
-}
-
-#[non_exhaustive]
-enum Template {
-    Tera(Contents),
-    Handlebars(Contents),
-    Liquid(Contents),
-    Unknown(EngineName, Contents),
-    NoEngine(Contents),
-}
-
-impl Template {
-    fn get_engine(&self) -> &'static str {
-        match self {
-            Template::Tera(_) => "tera",
-            Template::Handlebars(_) => "handlebars",
-            Template::Liquid(_) => "liquid",
-            Template::Unknown(_, _) => "unknown",
-            Template::NoEngine(_) => "no_engine",
-        }
-    }
-}
-
-struct Args {
-    template_file: Option<AbsolutePath>,
-    context_file: Option<AbsolutePath>,
-    output_file: Option<AbsolutePath>,
-    verbose: u8,
-    open: bool,
-    watch: Option<u64>,
-    stdout: bool,
-    stderr: bool,
-    engine: Option<TemplateEngine>,
-    engine_list: bool,
-    extension: Option<String>,
-}
-
-impl Args {
-    fn parse() -> Self {
-        let arg_matches = {
-            let about = format!(
-                "{description}\n\n  Author: {author}\n  License: {license}\n  Repository: {repository}\n{qrcode}",
-                description = env!("CARGO_PKG_DESCRIPTION"),
-                author = env!("CARGO_PKG_AUTHORS"),
-                license = env!("CARGO_PKG_LICENSE"),
-                repository = env!("CARGO_PKG_REPOSITORY"),
-                qrcode = qrcode_string(env!("CARGO_PKG_REPOSITORY"))
-            );
-
-            clap::Command::new(env!("CARGO_PKG_NAME"))
-            .version(env!("CARGO_PKG_VERSION"))
-            .about(about)
-            .arg(
-                Arg::new("template_file")
-                    .value_name("TEMPLATE FILE")
-                    .long_help(
-r#"The template file to render.
-    
-Unless using the `--context <CONTEXT FILE>` option, this requires either the 
-`<TEMPLATE NAME>.ctx.json` or the `default.ctx.json` context files to be present in the template's directory.
-
-[Example]
-
-.
-├── my_template.ctx.json
-└── my_template.html
-
-If `my_template.ctx.json` is missing, `default.ctx.json` will be loaded automatically from the template's directory.
-
-.
-├── default.ctx.json
-└── my_template.html
-
-If `default.ctx.json` is missing from the template directory, it will be loaded automatically from the current working directory, instead.
-
-[Output]
-    
-Unless using the `--output <OUTPUT FILE>` option, providing `<TEMPLATE FILE>`, produces a `<TEMPLATE NAME>.rendered.<EXTENSION>` file by default.
-
-.
-├── my_template.html
-└── my_template.rendered.html
-                   
-By NOT providing `<TEMPLATE FILE>`, STDIN mode is activated. Template data stream will be expected to come from STDIN, printing the results to STDOUT instead of writing to file."#
-                )
-                    .value_parser(value_parser!(AbsolutePath))
-                    .display_order(1)
-            ).arg(
-                Arg::new("context_file")
-                    .value_name("CONTEXT FILE")
-                    .long_help("Override default loading of the context file with the specified context file.")
-                    .short('c')
-                    .long("context")
-                    .value_parser(value_parser!(AbsolutePath))
-                    .display_order(2)
-            ).arg(
-                Arg::new("output_file")
-                    .value_name("OUTPUT FILE")
-                    .long_help("Override default output file path with the specified file path.")
-                    .short('o')
-                    .long("output")
-                    .value_parser(value_parser!(AbsolutePath))
-                    .display_order(3)
-            ).arg(
-                Arg::new("stdout")
-                    .long_help("Print rendered result to STDOUT.")
-                    .long("stdout")
-                    .action(clap::ArgAction::SetTrue)
-                    .display_order(4)
-            ).arg(
-                Arg::new("stderr")
-                    .long_help("Print rendered result to STDERR.")
-                    .long("stderr")
-                    .action(clap::ArgAction::SetTrue)
-                    .display_order(5)
-            ).arg(
-                Arg::new("open")
-                    .long_help("Open the rendered output file with a default software.")
-                    .long("open")
-                    .short('O')
-                    .action(clap::ArgAction::SetTrue)
-                    .display_order(6)
-            ).arg(
-                Arg::new("watch")
-                    .long_help("Constantly render changes in the template with the context file every 2 seconds by default.")
-                    .long("watch")
-                    .short('w')
-                    .value_name("[optional] SECONDS")
-                    .num_args(0..=1) // between 0 and 1 value occurrences
-                    .default_missing_value("2")
-                    .value_parser(value_parser!(u64))
-                    .display_order(7)
-            ).arg(
-                Arg::new("engine")
-                    .value_name("ENGINE NAME")
-                    .long_help(
-r#"Force rendering with the specified render engine.
-Use only when there is no magic comment or a template file extension available."#
-                )
-                    .long("engine")
-                    .short('e')
-                    .value_parser(value_parser!(TemplateEngine))
-                    .display_order(8)
-            ).arg(
-                Arg::new("engine_list")
-                    .long_help("Print supported engine list for the `--engine <ENGINE NAME>` option.")
-                    .long("engine-list")
-                    .action(clap::ArgAction::SetTrue)
-                    .display_order(9)
-            ).arg(
-                Arg::new("extension")
-                    .value_name("EXTENSION")
-                    .long_help("Force the underlying template engine to treat the template data as if it is a file of the given extension. This may affect escaping of special characters.")
-                    .long("extension")
-                    .short('x')
-                    .value_parser(value_parser!(String))
-                    .display_order(10)
-            ).arg(
-                Arg::new("verbose")
-                    .long_help(
-r#"Set the level of verbosity.
-    
-`-v` sets logging level to INFO
-    
-`-vv` sets logging level to DEBUG
-    
-`-vvv` sets logging level to TRACE
-    
-WARNING: Effects CLI / STDOUT output.
-Use the `--output` switch if you wish to commit the rendered output to file.
-Use the `--stderr` switch to avoid including the logger messages in the final output."#
-                )
-                .long("verbose")
-                .short('v')
-                .action(clap::ArgAction::Count)
-                .value_parser(value_parser!(u8))
-                .display_order(11)
-            )
-            .get_matches()
-        };
-
-        let err_msg = "Bad argument configuration";
-
-        Args {
-            template_file: arg_matches
-                .get_one::<AbsolutePath>("template_file")
-                .map(|v| v.to_owned()),
-
-            context_file: arg_matches
-                .get_one::<AbsolutePath>("context_file")
-                .map(|v| v.to_owned()),
-
-            output_file: arg_matches
-                .get_one::<AbsolutePath>("output_file")
-                .map(|v| v.to_owned()),
-
-            verbose: *arg_matches.get_one::<u8>("verbose").expect(err_msg),
-
-            open: *arg_matches.get_one::<bool>("open").expect(err_msg),
-
-            watch: arg_matches.get_one::<u64>("watch").map(|v| v.to_owned()),
-
-            stdout: *arg_matches.get_one::<bool>("stdout").expect(err_msg),
-
-            stderr: *arg_matches.get_one::<bool>("stderr").expect(err_msg),
-
-            engine: arg_matches
-                .get_one::<TemplateEngine>("engine")
-                .map(|v| v.to_owned()),
-
-            engine_list: *arg_matches.get_one::<bool>("engine_list").expect(err_msg),
-
-            extension: arg_matches
-                .get_one::<String>("extension")
-                .map(|v| v.to_owned()),
+            _ => {}
         }
     }
+    Ok(())
 }
 
-// impl<'arg_matches> Args<'arg_matches> {
-//     fn parse(arg_matches: &ArgMatches) -> Args {
-//         // let arg_matches = arg_matches();
-
-//         let err_msg = "Bad argument configuration";
-
-//         Args {
-//             template_file: arg_matches.get_one::<AbsolutePath>("template_file"),
-//             context_file: arg_matches.get_one::<AbsolutePath>("context_file"),
-//             output_file: arg_matches.get_one::<AbsolutePath>("output_file"),
-//             verbose: *arg_matches.get_one::<u8>("verbose").expect(err_msg),
-//             open: *arg_matches.get_one::<bool>("open").expect(err_msg),
-//             watch: arg_matches.get_one::<u64>("watch"),
-//             stdout: *arg_matches.get_one::<bool>("stdout").expect(err_msg),
-//             stderr: *arg_matches.get_one::<bool>("stderr").expect(err_msg),
-//             engine: arg_matches.get_one::<TemplateEngine>("engine"),
-//             engine_list: *arg_matches.get_one::<bool>("engine_list").expect(err_msg),
-//             extension: arg_matches.get_one::<String>("extension"),
-//         }
-//     }
-// }
-
 /// Write `content` to file `path` using BufWriter
 fn write_to_file<P: AsRef<Path>>(content: &str, path: P) -> Result<()> {
     let file = OpenOptions::new()
@@ -589,355 +90,172 @@ fn write_to_file<P: AsRef<Path>>(content: &str, path: P) -> Result<()> {
     Ok(())
 }
 
-impl From<&str> for Template {
-    /// Inspect the String contents for a magic comment `<!--template engine_name-->`, and return the appropriate `Template` enum variation for rendering.
-    fn from(contents: &str) -> Self {
-        let re = RegexBuilder::new(r#"<!--template\s+(?P<engine>\w+)\s?-->"#)
-            .case_insensitive(true)
-            .build()
-            .expect("Bad regex pattern.");
-
-        let mut re_caps = re.captures_iter(contents);
-
-        // We want to find only the first one without scanning the rest of the file
-        let mut re_iter = re.find_iter(contents);
-
-        if let Some(m) = re_iter.next() {
-            let found_match = m.as_str();
+/// Names the output of an `--each` item, replacing the `{{ key.path }}` placeholders of `pattern` by the item's values.
+fn output_path_for(pattern: &str, context: &Value) -> Result<PathBuf> {
+    let placeholder_re =
+        Regex::new(r"\{\{\s*(?P<path>[^{}\s]+)\s*\}\}").expect("Bad regex pattern.");
 
-            let contents = Rc::new(contents.replacen(found_match, "", 1).trim().to_owned());
+    let mut missing = None;
 
-            let cap = re_caps
-                .next()
-                .expect("Match without a capture? how is it possible?");
-
-            let engine = cap["engine"].to_lowercase();
-
-            log::debug!("Detected magic comment: `{engine}`");
-
-            match engine.as_str() {
-                "tera" => Template::Tera(contents),
-                "hbs" | "handlebars" => Template::Handlebars(contents),
-                "liq" | "liquid" => Template::Liquid(contents),
-                unknown_engine => Template::Unknown(unknown_engine.to_owned(), contents),
+    let path = placeholder_re.replace_all(pattern, |cap: &Captures| {
+        match get_path(context, &cap["path"]) {
+            Some(Value::String(s)) => s.to_owned(),
+            Some(value) if !value.is_null() => value.to_string(),
+            _ => {
+                missing.get_or_insert_with(|| cap["path"].to_owned());
+                String::new()
             }
-        } else {
-            Template::NoEngine(Rc::new(contents.to_owned()))
         }
-    }
-}
+    });
 
-impl<'arg> From<&TemplateData<'arg>> for Template {
-    /// Loads a template file into a Template enum type.
-    /// Decides on the engine type by first inspecting the file extension (`.tera`, `.hbs` or `.liq`).
-    /// If no special extension is provided then the contents of the template are inspected for the magic comment `<!--TEMPLATE engine_name-->`.
-    ///
-    /// Engine Names: `tera`, `handlebars` or `hbs`, `liquid` or `liq`
-    fn from(td: &TemplateData) -> Self {
-        // Checking for template file extension to determine the template engine.
-        // Notice the early returns.
-        if let Some(template_file) = td.file_path {
-            if let Some(extension) = template_file.extension() {
-                // if let Some(ref extension) = template_file.parts.extension {
-                let file_extension = &*extension.to_string_lossy();
-                // let file_extension = extension.as_str();
-
-                let contents = td.contents.clone();
-                match file_extension {
-                    "tera" => return Template::Tera(contents),
-                    "hbs" => return Template::Handlebars(contents),
-                    "liq" => return Template::Liquid(contents),
-                    _ => {} // ignore unknown extensions
-                };
-            }
-        }
-        // Scan template contents for the magic comment to return the proper Template kind.
-        (*td.contents.as_str()).into()
+    match missing {
+        Some(path) => bail!("`{path}` of the output pattern is missing from the context"),
+        None => Ok(PathBuf::from(path.into_owned())),
     }
 }
 
-// fn pretty_print(content: &str, language: &str) -> Result<()> {
-//     let bytes_content = content.as_bytes();
-
-//     PrettyPrinter::new()
-//         .language(language) // Default: auto-detect
-//         .line_numbers(false)
-//         .grid(true)
-//         .header(true)
-//         .input(bat::Input::from_bytes(bytes_content))
-//         .print()
-//         .context("Unable to pretty print.")?;
+/// Reports an error in watch mode and waits for the next round, printing a repeated error only once.
+/// Outside of watch mode, the error is returned.
+fn report_watch_error(
+    error: anyhow::Error,
+    watch: Option<u64>,
+    last_error: &mut Option<anyhow::Error>,
+) -> Result<()> {
+    let Some(secs) = watch else {
+        return Err(error);
+    };
 
-//     Ok(())
-// }
+    let is_repeated = last_error
+        .as_ref()
+        .is_some_and(|le| format!("{le:#}") == format!("{error:#}"));
 
-fn stdin_read() -> Result<String> {
-    let lines = std::io::stdin().lines();
-    let mut result = String::new();
-    for line in lines {
-        let l = line.context("Failed to read STDIN line")?;
-        result.push_str(&l);
-        result.push('\r');
-        result.push('\n');
+    if !is_repeated {
+        eprintln!("{:?}", error);
+        *last_error = Some(error);
     }
 
-    // let result = lines
-    //     .map(|line| {
-    //         let l = line.expect("Failed to read stdin line");
-    //         l + "\r\n"
-    //     })
-    //     .collect();
-
-    Ok(result)
-}
-
-#[non_exhaustive]
-#[derive(thiserror::Error, Debug)]
-pub enum RenditError {
-    #[error("Path must not be empty or root")]
-    EmptyPath,
-
-    #[error("Provided unsupported engine `{0}`")]
-    UnknownEngine(String),
-}
-
-struct TemplateData<'a> {
-    contents: Rc<String>,
-    file_path: Option<&'a AbsolutePath>,
-}
-
-// #[allow(unused)]
-struct ContextData {
-    context: serde_json::Value,
-    file_path: AbsolutePath,
-}
-
-struct RenderedTemplate(Rc<String>);
-
-enum DetectionMethod {
-    Auto,
-    Force(TemplateEngine),
+    thread::sleep(Duration::from_secs(secs));
+    Ok(())
 }
 
-impl From<Option<TemplateEngine>> for DetectionMethod {
-    fn from(te: Option<TemplateEngine>) -> Self {
-        match te {
-            Some(engine) => DetectionMethod::Force(engine),
-            None => DetectionMethod::Auto,
-        }
+/// Adds the environment under `env`, and then applies the `--set` overrides.
+fn apply_overrides(context_data: &mut ContextData, env: Option<&Value>, args: &Args) -> Result<()> {
+    if let Some(env) = env {
+        merge_values(
+            &mut context_data.context,
+            serde_json::json!({ "env": env }),
+            args.merge_arrays,
+        );
     }
-}
 
-impl From<Option<&TemplateEngine>> for DetectionMethod {
-    fn from(te: Option<&TemplateEngine>) -> Self {
-        match te {
-            Some(engine) => DetectionMethod::Force(*engine),
-            None => DetectionMethod::Auto,
-        }
+    for (path, value) in &args.overrides {
+        log::debug!("Context override: `{path}` = {value}");
+        context_data.set(path, value.clone())?;
     }
+    Ok(())
 }
 
-enum TemplateExtension<'a> {
-    Auto,
-    Force(&'a str),
-}
-
-impl<'a> From<Option<&'a String>> for TemplateExtension<'a> {
-    fn from(s: Option<&'a String>) -> Self {
-        match s {
-            Some(ext) => TemplateExtension::Force(ext),
-            None => TemplateExtension::Auto,
+/// Renders `template` once per line of newline-delimited JSON contexts from STDIN, streaming the results to STDOUT.
+///
+/// Each line is merged over the `base` context. A line that fails is reported, without stopping the stream.
+fn render_stream(
+    template: &PreparedTemplate,
+    base: &ContextData,
+    env: Option<&Value>,
+    args: &Args,
+) -> Result<()> {
+    let mut stdout = std::io::stdout().lock();
+    let mut failed = 0;
+
+    for (index, line) in std::io::stdin().lines().enumerate() {
+        let line_number = index + 1;
+        let line = line.context("Failed to read STDIN line")?;
+
+        if line.trim().is_empty() {
+            continue;
         }
-    }
-}
 
-fn render<'a>(
-    template_data: &'a TemplateData,
-    context_data: &'a ContextData,
-    engine_detection: DetectionMethod,
-    template_extension: TemplateExtension,
-) -> Result<RenderedTemplate> {
-    // ) -> Result<RenderedTemplate<'a>> {
-    // let default_language = "html";
-
-    // let template_language = &*match template_data.file_path {
-    //     Some(p) => match p.extension() {
-    //         Some(ext) => ext.to_string_lossy(),
-    //         None => Cow::Borrowed(default_language),
-    //     },
-    //     None => Cow::Borrowed(default_language),
-    // };
-
-    // let template_path = template_data.file_path.clone();
-
-    let template = match engine_detection {
-        DetectionMethod::Auto => {
-            log::debug!("Detection method: Automatic");
-            Template::from(template_data)
-        }
-        DetectionMethod::Force(engine) => {
-            log::debug!("Detection method: Manual = `{engine}`");
-            let contents = template_data.contents.clone();
-            match engine {
-                TemplateEngine::Tera => Template::Tera(contents),
-                TemplateEngine::Liquid => Template::Liquid(contents),
-                TemplateEngine::Handlebars => Template::Handlebars(contents),
-                TemplateEngine::None => Template::NoEngine(contents),
+        let rendered = serde_json::from_str(&line)
+            .context("Invalid JSON context")
+            .and_then(|context| {
+                let mut context_data = base.clone();
+                merge_values(&mut context_data.context, context, args.merge_arrays);
+                apply_overrides(&mut context_data, env, args)?;
+                Ok(template.render(&context_data)?)
+            });
+
+        let record = match rendered {
+            Ok(rendered) if args.ndjson_output => {
+                format!("{}\n", serde_json::json!({ "output": rendered.as_str() }))
             }
-        }
-    };
-
-    log::debug!("Selected engine: `{}`", template.get_engine());
-
-    let result = match template {
-        Template::Tera(contents) => {
-            let context = tera::Context::from_value(context_data.context.clone())
-                .context("Tera rejected Context object.")?;
-
-            // match Tera::one_off(&contents, &context, true) {
-            //     Ok(rendered) => rendered,
-            //     Err(e) => {
-            //         if let Some(source) = e.source() {
-            //             log::error!("{source}");
-            //         }
-            //         return Err(anyhow::Error::new(e).context("Unable to render template."));
-            //     }
-            // }
-
-            let templates_root_file = if let Some(template_file) = template_data.file_path {
-                Cow::Borrowed(template_file)
-            } else {
-                let abs_path: AbsolutePath = std::env::current_exe()
-                    .context("Failed to get current exe path")?
-                    .into();
-                // Cow::Owned(abs_path.into_inner())
-                Cow::Owned(abs_path)
-            };
-
-            let templates_home_dir = templates_root_file
-                .parent()
-                .context("Failed to get home directory")?;
-
-            let templates_home_dir_glob = templates_home_dir.join("**");
-
-            let templates_home_dir_glob = templates_home_dir_glob.join("*.*");
-
-            let templates_home_dir_glob = templates_home_dir_glob.to_string_lossy();
+            Ok(rendered) => format!("{rendered}{}", args.separator),
+            Err(e) => {
+                failed += 1;
+                eprintln!("Line {line_number}: {e:#}");
 
-            log::debug!("Tera templates path: {templates_home_dir_glob}");
-
-            // TODO: Better to create an instance of `Tera::default()` and have a deep scan for the templates to add only the references ones into a HashSet, than to add every file in the template's directory.
-            // let mut tera = Tera::default();
-
-            // let template_references: Vec<(AbsolutePath, Option<String>)> =
-            //     find_template_references(&contents, template_path)
-            //         .into_iter()
-            //         .map(|p| {
-            //             let file_name = p.file_name().map(|fp| fp.to_string_lossy().to_string());
-            //             (p, file_name)
-            //         })
-            //         .collect();
-
-            // tera.add_template_files(template_references)
-            //     .context("Tera failed loading partial template files")?;
-
-            let mut tera =
-                Tera::new(&templates_home_dir_glob).context("Unable to create Tera instance")?;
-
-            // Force extension or auto detect (default `.html`)
-            let template_type = if let TemplateExtension::Force(ext) = template_extension {
-                log::debug!("Tera: Forcing extension \"{ext}\"");
-                Cow::Borrowed(ext)
-            } else if let Some(path) = template_data.file_path {
-                match path.extension() {
-                    Some(ext) => ext.to_string_lossy(),
-                    None => Cow::Borrowed("html"),
+                if !args.ndjson_output {
+                    continue;
                 }
-                // match path.parts.extension {
-                //     Some(ref ext) => Cow::Borrowed(ext.as_str()),
-                //     None => Cow::Borrowed("html"),
-                // }
-            } else {
-                Cow::Borrowed("html")
-            };
+                format!(
+                    "{}\n",
+                    serde_json::json!({ "line": line_number, "error": format!("{e:#}") })
+                )
+            }
+        };
 
-            log::debug!("Tera: Using extension \"{template_type}\"");
-            let in_memory_template = format!("__in_memory__.{}", template_type);
+        stdout
+            .write_all(record.as_bytes())
+            .and_then(|_| stdout.flush())
+            .context("Failed to write to STDOUT")?;
+    }
 
-            // Adds a virtual in-memory file for the main template. We need the `.html` extension to enforce HTML escaping.
-            tera.add_raw_template(&in_memory_template, &contents)
-                .context("Tera is unable to add the main template as raw template.")?;
+    if failed > 0 {
+        bail!("{failed} of the NDJSON lines failed to render");
+    }
+    Ok(())
+}
 
-            let rendered = tera
-                .render(&in_memory_template, &context)
-                .context("Tera is unable to render the template.")?;
+/// Reads the template from STDIN as is, line endings included.
+fn stdin_read() -> Result<String> {
+    let mut result = String::new();
+    std::io::stdin()
+        .read_to_string(&mut result)
+        .context("Failed to read STDIN")?;
+    Ok(result)
+}
 
-            Rc::new(rendered)
-        }
-        Template::Handlebars(contents) => {
-            let handlebars = Handlebars::new();
-            let render = handlebars.render_template(&contents, &context_data.context);
-            // match render {
-            //     Ok(contents) => contents,
-            //     Err(e) => {
-            //         if let Some(source) = e.source() {
-            //             if let Some(template_error) = source.downcast_ref::<TemplateError>() {
-            //                 let template_error_string = format!("{template_error}");
-            //                 pretty_print(&template_error_string, template_language)?;
-            //             }
-            //         }
-            //         return Err(anyhow::Error::new(e).context("Unable to render template."));
-            //     }
-            // }
-            let rendered = render.context("Handlebars is unable to render the template.")?;
-
-            Rc::new(rendered)
-        }
-        Template::Liquid(contents) => {
-            // TODO: Enable partials using `find_template_references()`
-            let template = liquid::ParserBuilder::with_stdlib()
-                .build()
-                .context("Liquid is unable to build the parser.")?
-                .parse(&contents);
-
-            // let template = match template {
-            //     Ok(t) => t,
-            //     Err(e) => {
-            //         let template_error_string = format!("{e}");
-            //         pretty_print(&template_error_string, template_language)?;
-            //         // eprintln!("{e}");
-            //         return Err(anyhow::Error::new(e).context("Unable to parse template."));
-            //     }
-            // };
-            let template = template.context("Liquid is unable to parse the template.")?;
-
-            let globals = liquid::object!(&context_data.context);
-
-            let rendered = template
-                .render(&globals)
-                .context("Liquid is unable to render the template.")?;
-
-            Rc::new(rendered)
-        }
-        Template::Unknown(engine, _) => return Err(anyhow!("Unknown template engine: `{engine}`")),
-        Template::NoEngine(raw) => raw,
-    };
-    Ok(RenderedTemplate(result))
+/// Write `content` to STDOUT as is, without appending a line break
+fn write_to_stdout(content: &str) -> Result<()> {
+    let mut stdout = std::io::stdout().lock();
+    stdout
+        .write_all(content.as_bytes())
+        .and_then(|_| stdout.flush())
+        .context("Failed to write to STDOUT")
 }
 
 fn main() -> Result<()> {
     // setup_panic!();
     let args = Args::parse();
 
-    if args.engine_list {
-        let supported_engines = all::<TemplateEngine>().collect::<Vec<_>>();
+    #[allow(unused_mut)]
+    let mut registry = EngineRegistry::default();
 
-        for (i, engine) in supported_engines.iter().enumerate() {
-            println!("{}. {}", i + 1, engine.to_string().as_str().to_lowercase());
+    #[cfg(feature = "engine-subst")]
+    registry.register(SubstRenderer::new().env(args.subst_env));
+
+    if args.engine_list {
+        for (i, engine) in registry.iter().enumerate() {
+            println!("{}. {}", i + 1, engine.info().name);
         }
         process::exit(0);
     }
 
+    if let DetectionMethod::Force(engine) = DetectionMethod::from(args.engine.as_ref()) {
+        registry
+            .try_get_chain(&engine)
+            .context("Please try one of the supported engines in `--engine-list`")?;
+    }
+
     let log_level = match args.verbose {
         1 => LevelFilter::Info,
         2 => LevelFilter::Debug,
@@ -953,6 +271,13 @@ fn main() -> Result<()> {
     )
     .context("Unable to initialize the logger.")?;
 
+    if let Some(root) = &args.root {
+        if !root.is_dir() {
+            bail!("The root \"{root}\" is not a directory");
+        }
+        log::debug!("Root directory: \"{root}\"");
+    }
+
     let mut has_looped = false;
 
     let mut last_error: Option<anyhow::Error> = None;
@@ -964,145 +289,232 @@ fn main() -> Result<()> {
             log::info!("Rendering file: \"{template_file}\"");
 
             // File Mode
-            TemplateData {
-                contents: {
-                    let contents = fs::read_to_string(template_file).with_context(|| {
-                        format!("Unable to load template file \"{template_file}\"")
-                    })?;
-                    Rc::new(contents)
-                },
-                file_path: {
-                    // let path: &Path = template_file.as_ref();
-                    // Some(path.try_into()?)
-                    Some(template_file)
-                },
-            }
+            TemplateData::from_file(template_file.to_owned())?
         } else {
             // STDIN Mode
-            TemplateData {
-                contents: Rc::new(stdin_read()?),
-                file_path: None,
+            TemplateData::from_source(stdin_read()?)
+        };
+
+        let options = RenderOptions {
+            extension: args.extension.as_ref().into(),
+            strict: args.strict,
+            line_endings: args.line_endings,
+            root: args.root.clone(),
+            include_dirs: args.include_dirs.clone(),
+        };
+
+        let template = match prepare(
+            &registry,
+            &template_data,
+            args.engine.as_ref().into(),
+            &options,
+        ) {
+            Ok(template) => template,
+            Err(e) => {
+                report_watch_error(e.into(), args.watch, &mut last_error)?;
+                continue 'watch;
             }
         };
 
-        let context_file_arg = args.context_file.as_ref();
+        // The rest of the front matter is applied by the template itself.
+        let front_matter = template.front_matter();
 
-        let context_data = {
-            let context_file = if let Some(context_file) = context_file_arg {
-                context_file.to_owned()
-            } else if let Some(template_file) = template_file_arg {
-                let ctx_path = template_file.with_extension("ctx.json");
+        // The paths of the front matter are relative to the template's directory.
+        let template_dir = template_file_arg
+            .and_then(|template_file| template_file.parent())
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
 
-                if ctx_path.exists() {
-                    ctx_path.into()
-                } else {
-                    let relative_default_ctx_file =
-                        template_file.with_file_name(DEFAULT_CONTEXT_FILE);
-                    if relative_default_ctx_file.exists() {
-                        relative_default_ctx_file.into()
-                    } else {
-                        // Default context from Working directory
-                        PathBuf::from(DEFAULT_CONTEXT_FILE).into()
+        let output_file: Option<AbsolutePath> = args.output_file.clone().or_else(|| {
+            front_matter
+                .output
+                .as_ref()
+                .map(|output| template_dir.join(output).into())
+        });
+
+        if args.each && template_file_arg.is_some() && output_file.is_none() && !args.stdout {
+            bail!("`--each` requires an `--output` pattern to name the rendered files, e.g. `--output 'letters/{{{{ id }}}}.html'`");
+        }
+
+        let context_data = {
+            let context_files: Vec<AbsolutePath> = if !args.context_files.is_empty() {
+                args.context_files.clone()
+            } else if !front_matter.context.is_empty() {
+                front_matter
+                    .context
+                    .iter()
+                    .map(|context_file| template_dir.join(context_file).into())
+                    .collect()
+            } else {
+                let context_file = template_file_arg
+                    .and_then(|template_file| {
+                        find_context_file(template_file).or_else(|| {
+                            find_context_file(template_file.with_file_name(DEFAULT_CONTEXT_NAME))
+                        })
+                    })
+                    // Default context from Working directory
+                    .or_else(|| find_context_file(DEFAULT_CONTEXT_NAME));
+
+                match context_file {
+                    Some(context_file) => vec![context_file.into()],
+                    // `--set`, the environment or the `--ndjson` lines alone are enough for a context.
+                    None if args.ndjson
+                        || !args.overrides.is_empty()
+                        || args.env_prefix.is_some()
+                        || args.env_file.is_some() =>
+                    {
+                        Vec::new()
                     }
+                    None => vec![PathBuf::from(DEFAULT_CONTEXT_FILE).into()],
                 }
-            } else {
-                // Default context from Working directory
-                PathBuf::from(DEFAULT_CONTEXT_FILE).into()
             };
 
-            log::info!("Context file: \"{context_file}\"");
-
-            let contents = fs::read_to_string(&context_file)
-                .with_context(|| format!("Unable to load context file \"{context_file}\""))?;
-
-            ContextData {
-                // context: contents.into(), // not the way to do it as some engines did not recognize the JSON structure.
-                context: match serde_json::from_str(&contents).with_context(|| {
-                    format!("Unable to parse JSON context from file \"{context_file}\"")
-                }) {
-                    Ok(ctx) => ctx,
-                    Err(e) => match args.watch {
-                        Some(secs) => {
-                            if let Some(ref le) = last_error {
-                                if format!("{le:#}") == format!("{e:#}") {
-                                    thread::sleep(Duration::from_secs(secs));
-                                    continue 'watch;
-                                }
-                            };
-                            eprintln!("{:?}", e);
-                            last_error = Some(e);
-
-                            thread::sleep(Duration::from_secs(secs));
-                            continue 'watch;
-                        }
-                        None => return Err(e),
-                    },
-                },
-                file_path: context_file,
+            for context_file in &context_files {
+                log::info!("Context file: \"{context_file}\"");
+
+                if let Some(root) = &args.root {
+                    restrict_context(context_file, root)?;
+                }
+            }
+
+            match ContextData::from_files(context_files, args.merge_arrays) {
+                Ok(ctx) => ctx,
+                Err(e @ RenditError::ContextParse { .. }) => {
+                    report_watch_error(e.into(), args.watch, &mut last_error)?;
+                    continue 'watch;
+                }
+                Err(e) => return Err(e.into()),
             }
         };
 
-        let rendered_template = match render(
-            &template_data,
-            &context_data,
-            args.engine.into(),
-            args.extension.as_ref().into(),
-        ) {
-            Ok(r) => r,
-            Err(e) => match args.watch {
-                Some(secs) => {
-                    if let Some(ref le) = last_error {
-                        if format!("{le:#}") == format!("{e:#}") {
-                            thread::sleep(Duration::from_secs(secs));
-                            continue 'watch;
-                        }
-                    };
-                    eprintln!("{:?}", e);
-                    last_error = Some(e);
+        let env = if args.env_prefix.is_some() || args.env_file.is_some() {
+            let mut vars = Vec::new();
 
-                    thread::sleep(Duration::from_secs(secs));
-                    continue 'watch;
+            if let Some(env_file) = &args.env_file {
+                log::info!("Environment file: \"{env_file}\"");
+
+                if let Some(root) = &args.root {
+                    env_file.restrict_to(root)?;
                 }
+                vars.extend(read_env_file(env_file.to_owned())?);
+            }
 
-                None => return Err(e),
-            },
+            // Added last, so the process environment takes precedence over the file.
+            if let Some(prefix) = &args.env_prefix {
+                vars.extend(read_env_vars(prefix)?);
+            }
+
+            Some(env_object(
+                vars,
+                args.env_prefix.as_deref().unwrap_or_default(),
+            ))
+        } else {
+            None
         };
 
-        if args.stderr {
-            eprintln!("{}", rendered_template.0);
+        if args.ndjson {
+            return render_stream(&template, &context_data, env.as_ref(), &args);
         }
 
-        if args.stdout {
-            println!("{}", rendered_template.0);
-        }
+        // With `--each`, every item of the context is rendered on its own.
+        let contexts = if args.each {
+            let Value::Array(items) = context_data.context else {
+                bail!(
+                    "`--each` requires the context to be an array, such as the rows of a CSV file"
+                );
+            };
+            log::info!("Rendering {} context items", items.len());
+
+            items
+                .into_iter()
+                .map(|context| ContextData {
+                    context,
+                    file_path: context_data.file_path.clone(),
+                })
+                .collect()
+        } else {
+            vec![context_data]
+        };
 
-        // Output stages
-        if let Some(ref output_arg) = args.output_file {
-            log::info!("Rendered output file: \"{output_arg}\"");
-            write_to_file(&rendered_template.0, &output_arg)?;
+        for (index, mut context_data) in contexts.into_iter().enumerate() {
+            apply_overrides(&mut context_data, env.as_ref(), &args)?;
 
-            if !has_looped && args.open {
-                log::info!("Opening: \"{output_arg}\"");
-                opener::open(&output_arg)?;
+            let rendered_template = match template.render(&context_data) {
+                Ok(r) => r,
+                Err(e) => {
+                    let e = match args.each {
+                        true => {
+                            anyhow::Error::new(e).context(format!("Unable to render item {index}"))
+                        }
+                        false => e.into(),
+                    };
+                    report_watch_error(e, args.watch, &mut last_error)?;
+                    continue 'watch;
+                }
+            };
+
+            if args.stderr {
+                eprint!("{}", rendered_template.0);
             }
-        } else if let Some(template_file) = template_file_arg {
-            let output_path: AbsolutePath = rendered_path(template_file).into();
-            log::info!("Rendered output file: \"{output_path}\"");
-            write_to_file(&rendered_template.0, &output_path)?;
-
-            if !has_looped && args.open {
-                log::info!("Opening: \"{output_path}\"");
-                opener::open(&output_path)?;
+
+            if args.stdout {
+                write_to_stdout(&rendered_template.0)?;
+            }
+
+            // Output stages
+            if let Some(ref output_arg) = output_file {
+                let output_path: PathBuf = if args.each {
+                    output_path_for(&output_arg.to_string(), &context_data.context)
+                        .with_context(|| format!("Unable to name the output of item {index}"))?
+                } else {
+                    output_arg.to_path_buf()
+                };
+                let output_path: AbsolutePath = output_path.into();
+
+                if let Some(root) = &args.root {
+                    output_path.restrict_to(root)?;
+                }
+
+                if let Some(parent) = output_path.parent() {
+                    fs::create_dir_all(parent).with_context(|| {
+                        format!(
+                            "Unable to create directory: \"{}\"",
+                            parent.to_string_lossy()
+                        )
+                    })?;
+                }
+
+                log::info!("Rendered output file: \"{output_path}\"");
+                write_to_file(&rendered_template.0, &output_path)?;
+
+                if !has_looped && args.open {
+                    log::info!("Opening: \"{output_path}\"");
+                    opener::open(&output_path)?;
+                }
+            } else if let Some(template_file) = template_file_arg.filter(|_| !args.each) {
+                let output_path: AbsolutePath = registry.rendered_path(template_file).into();
+
+                if let Some(root) = &args.root {
+                    output_path.restrict_to(root)?;
+                }
+                log::info!("Rendered output file: \"{output_path}\"");
+                write_to_file(&rendered_template.0, &output_path)?;
+
+                if !has_looped && args.open {
+                    log::info!("Opening: \"{output_path}\"");
+                    opener::open(&output_path)?;
+                }
+            } else if !args.stdout {
+                // let pretty_print_preconditions = [args.pretty, args.verbose > 0];
+                //     if pretty_print_preconditions.iter().any(|&c| c) {
+                //         pretty_print(&result, Some(template_extension))
+                //     } else {
+                //         println!("{result}");
+                //     }
+                write_to_stdout(&rendered_template.0)?;
             }
-        } else if !args.stdout {
-            // let pretty_print_preconditions = [args.pretty, args.verbose > 0];
-            //     if pretty_print_preconditions.iter().any(|&c| c) {
-            //         pretty_print(&result, Some(template_extension))
-            //     } else {
-            //         println!("{result}");
-            //     }
-            println!("{}", rendered_template.0);
         }
+
         if let Some(sleep_time) = args.watch {
             log::debug!("Watch mode is activated: Rendering every {sleep_time} seconds");
             thread::sleep(Duration::from_secs(sleep_time));
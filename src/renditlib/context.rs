@@ -0,0 +1,877 @@
+use serde_json::Value;
+use std::{collections::BTreeMap, fs, path::Path};
+
+use super::{
+    error::{BoxError, RenditError},
+    path::AbsolutePath,
+};
+
+/// Extensions of the context files that are looked up next to a template, in order of precedence.
+pub const CONTEXT_EXTENSIONS: &[&str] = &[
+    "ctx.json",
+    "ctx.yaml",
+    "ctx.yml",
+    "ctx.toml",
+    "ctx.json5",
+    "ctx.csv",
+    "ctx.tsv",
+];
+
+/// The file formats a context can be loaded from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ContextFormat {
+    Json,
+    Json5,
+    Yaml,
+    Toml,
+    /// Comma separated values, loaded as an array of rows keyed by the header row.
+    Csv,
+    /// Tab separated values, loaded like [`ContextFormat::Csv`].
+    Tsv,
+}
+
+impl ContextFormat {
+    /// Decides on the format by the file extension. Unknown extensions are read as JSON.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Self {
+        Self::from_extension(path).unwrap_or(ContextFormat::Json)
+    }
+
+    /// Decides on the format by the file extension, if it is a known one.
+    pub fn from_extension<P: AsRef<Path>>(path: P) -> Option<Self> {
+        let extension = path.as_ref().extension()?.to_string_lossy().to_lowercase();
+
+        match extension.as_str() {
+            "json" => Some(ContextFormat::Json),
+            "json5" => Some(ContextFormat::Json5),
+            "yaml" | "yml" => Some(ContextFormat::Yaml),
+            "toml" => Some(ContextFormat::Toml),
+            "csv" => Some(ContextFormat::Csv),
+            "tsv" => Some(ContextFormat::Tsv),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ContextFormat::Json => "JSON",
+            ContextFormat::Json5 => "JSON5",
+            ContextFormat::Yaml => "YAML",
+            ContextFormat::Toml => "TOML",
+            ContextFormat::Csv => "CSV",
+            ContextFormat::Tsv => "TSV",
+        }
+    }
+
+    /// Parses `contents` into a JSON value. Fails with the error and its (line, column) if known.
+    pub fn parse(&self, contents: &str) -> Result<Value, (BoxError, Option<(usize, usize)>)> {
+        match self {
+            ContextFormat::Json => serde_json::from_str(contents).map_err(|e| {
+                let location = (e.line(), e.column());
+                (e.into(), Some(location))
+            }),
+            ContextFormat::Json5 => json5::from_str(contents).map_err(|e| {
+                let json5::Error::Message { location, .. } = &e;
+                let location = location.as_ref().map(|l| (l.line, l.column));
+                (e.into(), location)
+            }),
+            ContextFormat::Yaml => serde_yaml::from_str(contents).map_err(|e| {
+                let location = e.location().map(|l| (l.line(), l.column()));
+                (e.into(), location)
+            }),
+            ContextFormat::Toml => match toml::from_str::<toml::Value>(contents) {
+                Ok(value) => Ok(toml_to_json(value)),
+                Err(e) => {
+                    let location = e.span().map(|span| line_column(contents, span.start));
+                    Err((e.message().to_owned().into(), location))
+                }
+            },
+            // The CSV errors already tell the record and line.
+            ContextFormat::Csv => parse_rows(contents, b',').map_err(|e| (e.into(), None)),
+            ContextFormat::Tsv => parse_rows(contents, b'\t').map_err(|e| (e.into(), None)),
+        }
+    }
+}
+
+/// Converts TOML into JSON, keeping dates and times as strings.
+fn toml_to_json(value: toml::Value) -> Value {
+    match value {
+        toml::Value::String(s) => Value::String(s),
+        toml::Value::Integer(i) => i.into(),
+        toml::Value::Float(f) => f.into(),
+        toml::Value::Boolean(b) => b.into(),
+        toml::Value::Datetime(dt) => Value::String(dt.to_string()),
+        toml::Value::Array(items) => items.into_iter().map(toml_to_json).collect(),
+        toml::Value::Table(table) => table
+            .into_iter()
+            .map(|(k, v)| (k, toml_to_json(v)))
+            .collect(),
+    }
+}
+
+/// Reads delimited rows into an array of objects, keyed by the header row.
+fn parse_rows(contents: &str, delimiter: u8) -> Result<Value, csv::Error> {
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .from_reader(contents.as_bytes());
+
+    let headers = reader.headers()?.clone();
+
+    let rows = reader
+        .records()
+        .map(|record| {
+            Ok(headers
+                .iter()
+                .zip(record?.iter())
+                .map(|(key, field)| (key.to_owned(), infer_value(field)))
+                .collect::<serde_json::Map<_, _>>()
+                .into())
+        })
+        .collect::<Result<Vec<Value>, csv::Error>>()?;
+
+    Ok(Value::Array(rows))
+}
+
+/// Types a CSV field: booleans and numbers (`42`, `-1.50`) are converted, anything else stays a string.
+///
+/// Numbers with leading zeros, such as zip codes or phone numbers, are kept as strings, and so are
+/// integers that don't fit in 64 bits, such as long IDs (`12345678901234567890123`).
+/// Every decimal is converted, so a column of prices has the same type on every row.
+fn infer_value(field: &str) -> Value {
+    let digits = field.strip_prefix('-').unwrap_or(field);
+    let (integer, fraction) = match digits.split_once('.') {
+        Some((integer, fraction)) => (integer, Some(fraction)),
+        None => (digits, None),
+    };
+
+    let is_digits = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+    let is_number = is_digits(integer)
+        && (integer == "0" || !integer.starts_with('0'))
+        && fraction.is_none_or(is_digits);
+
+    if is_number {
+        let number = match fraction {
+            None => field
+                .parse::<i64>()
+                .map(Into::into)
+                .or_else(|_| field.parse::<u64>().map(Into::into))
+                .ok(),
+            Some(_) => field
+                .parse::<f64>()
+                .ok()
+                .and_then(serde_json::Number::from_f64),
+        };
+
+        if let Some(n) = number {
+            return Value::Number(n);
+        }
+    }
+
+    match field.to_lowercase().as_str() {
+        "true" => Value::Bool(true),
+        "false" => Value::Bool(false),
+        _ => Value::String(field.to_owned()),
+    }
+}
+
+/// Returns the 1-based (line, column) of a byte offset.
+fn line_column(contents: &str, offset: usize) -> (usize, usize) {
+    let before = &contents[..offset.min(contents.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before.chars().rev().take_while(|&c| c != '\n').count() + 1;
+    (line, column)
+}
+
+/// How arrays are combined when a context is merged over another.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ArrayMerge {
+    /// The array of the later context replaces the earlier one.
+    #[default]
+    Replace,
+    /// The items of the later context are appended to the earlier array.
+    Append,
+}
+
+impl From<&str> for ArrayMerge {
+    fn from(value: &str) -> Self {
+        match value.to_lowercase().as_str() {
+            "append" => ArrayMerge::Append,
+            _ => ArrayMerge::Replace,
+        }
+    }
+}
+
+/// Deep merges `overlay` into `base`: objects are merged recursively, anything else is replaced.
+pub fn merge_values(base: &mut Value, overlay: Value, arrays: ArrayMerge) {
+    match (base, overlay) {
+        (Value::Object(base), Value::Object(overlay)) => {
+            for (key, value) in overlay {
+                match base.get_mut(&key) {
+                    Some(existing) => merge_values(existing, value, arrays),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (Value::Array(base), Value::Array(overlay)) if arrays == ArrayMerge::Append => {
+            base.extend(overlay);
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+/// How many `null` items [`set_path`] may pad an array with, so that a typo such as `a.4000000000` doesn't exhaust memory.
+const MAX_ARRAY_PADDING: usize = 1000;
+
+enum Segment<'a> {
+    Key(&'a str),
+    Index(usize),
+}
+
+/// Splits `a.b[0].c` (or `a.b.0.c`) into its keys and array indexes.
+fn parse_path(path: &str) -> Result<Vec<Segment<'_>>, String> {
+    let mut segments = Vec::new();
+
+    for part in path.split('.') {
+        let (key, mut indexes) = match part.find('[') {
+            Some(i) => (&part[..i], &part[i..]),
+            None => (part, ""),
+        };
+
+        match key.parse::<usize>() {
+            Ok(index) => segments.push(Segment::Index(index)),
+            Err(_) if !key.is_empty() => segments.push(Segment::Key(key)),
+            Err(_) if indexes.is_empty() || segments.is_empty() => {
+                return Err(String::from("empty key"))
+            }
+            Err(_) => {}
+        }
+
+        while !indexes.is_empty() {
+            let index = indexes
+                .strip_prefix('[')
+                .and_then(|rest| rest.split_once(']'))
+                .and_then(|(index, rest)| Some((index.parse::<usize>().ok()?, rest)));
+
+            match index {
+                Some((index, rest)) => {
+                    segments.push(Segment::Index(index));
+                    indexes = rest;
+                }
+                None => return Err(format!("bad array index in `{part}`")),
+            }
+        }
+    }
+    Ok(segments)
+}
+
+/// Returns the value at a dotted `path` such as `a.b[0].c` or `a.b.0.c`, if there is one.
+pub fn get_path<'a>(context: &'a Value, path: &str) -> Option<&'a Value> {
+    parse_path(path)
+        .ok()?
+        .into_iter()
+        .try_fold(context, |target, segment| match (target, segment) {
+            (Value::Object(map), Segment::Key(key)) => map.get(key),
+            (Value::Object(map), Segment::Index(index)) => map.get(&index.to_string()),
+            (Value::Array(items), Segment::Index(index)) => items.get(index),
+            _ => None,
+        })
+}
+
+/// Sets the value at a dotted `path` such as `a.b[0].c` or `a.b.0.c`, creating missing objects and arrays on the way.
+///
+/// A numeric key indexes an array, or is a plain key of an existing object.
+/// Arrays are padded with `null` up to the given index, by no more than 1000 items.
+pub fn set_path(context: &mut Value, path: &str, value: Value) -> Result<(), RenditError> {
+    let error = |reason: String| RenditError::ContextSet {
+        path: path.to_owned(),
+        reason,
+    };
+
+    let segments = parse_path(path).map_err(error)?;
+
+    let mut target = context;
+
+    for segment in segments {
+        if target.is_null() {
+            *target = match segment {
+                Segment::Key(_) => Value::Object(Default::default()),
+                Segment::Index(_) => Value::Array(Vec::new()),
+            };
+        }
+
+        target = match (target, segment) {
+            (Value::Object(map), Segment::Key(key)) => map.entry(key).or_insert(Value::Null),
+            (Value::Object(map), Segment::Index(index)) => {
+                map.entry(index.to_string()).or_insert(Value::Null)
+            }
+            (Value::Array(items), Segment::Index(index)) => {
+                if index >= items.len() {
+                    if index - items.len() > MAX_ARRAY_PADDING {
+                        return Err(error(format!(
+                            "index {index} is more than {MAX_ARRAY_PADDING} items past the end of the array"
+                        )));
+                    }
+                    items.resize(index + 1, Value::Null);
+                }
+                &mut items[index]
+            }
+            (Value::Array(_), Segment::Key(key)) => {
+                return Err(error(format!("`{key}` is not an index of an array")))
+            }
+            (other, _) => {
+                let kind = match other {
+                    Value::String(_) => "a string",
+                    Value::Number(_) => "a number",
+                    _ => "a boolean",
+                };
+                return Err(error(format!(
+                    "found {kind} where an object or an array was expected"
+                )));
+            }
+        };
+    }
+
+    *target = value;
+    Ok(())
+}
+
+/// Builds a nested object out of the environment variables that start with `prefix`.
+///
+/// The prefix is stripped and `__` separates nesting levels, so `RENDIT_DB__HOST` becomes `{"DB": {"HOST": ...}}`.
+/// A variable that is repeated in `vars` takes its last value.
+pub fn env_object<I>(vars: I, prefix: &str) -> Value
+where
+    I: IntoIterator<Item = (String, String)>,
+{
+    let vars: BTreeMap<_, _> = vars
+        .into_iter()
+        .filter_map(|(key, value)| Some((key.strip_prefix(prefix)?.to_owned(), value)))
+        .filter(|(key, _)| !key.is_empty())
+        .collect();
+
+    let mut object = serde_json::Map::new();
+
+    'vars: for (key, value) in vars {
+        let mut parts: Vec<&str> = key.split("__").filter(|p| !p.is_empty()).collect();
+        let Some(last) = parts.pop() else {
+            continue;
+        };
+
+        let mut target = &mut object;
+        for part in parts {
+            let entry = target
+                .entry(part)
+                .or_insert_with(|| Value::Object(Default::default()));
+
+            target = match entry {
+                Value::Object(map) => map,
+                _ => {
+                    log::warn!("Environment variable `{prefix}{key}` conflicts with `{prefix}{part}`, skipping");
+                    continue 'vars;
+                }
+            };
+        }
+        target.insert(last.to_owned(), Value::String(value));
+    }
+    Value::Object(object)
+}
+
+/// Reads the variables of the process environment that start with `prefix`.
+///
+/// Fails on a matching variable that isn't valid UTF-8, while the other variables may be anything.
+pub fn read_env_vars(prefix: &str) -> Result<Vec<(String, String)>, RenditError> {
+    std::env::vars_os()
+        .filter(|(key, _)| key.as_encoded_bytes().starts_with(prefix.as_bytes()))
+        .map(
+            |(key, value)| match (key.into_string(), value.into_string()) {
+                (Ok(key), Ok(value)) => Ok((key, value)),
+                (Ok(key), Err(_)) => Err(RenditError::EnvVar(key)),
+                (Err(key), _) => Err(RenditError::EnvVar(key.to_string_lossy().into_owned())),
+            },
+        )
+        .collect()
+}
+
+/// Reads the variables of a `.env` file, without setting them in the process environment.
+pub fn read_env_file<P: Into<AbsolutePath>>(path: P) -> Result<Vec<(String, String)>, RenditError> {
+    let path = path.into();
+
+    let to_error = |e: dotenvy::Error| match e {
+        dotenvy::Error::Io(e) => RenditError::ContextFile(path.to_path_buf(), e),
+        e => RenditError::ContextParse {
+            path: path.to_path_buf(),
+            format: "dotenv",
+            location: None,
+            source: e.into(),
+        },
+    };
+
+    dotenvy::from_path_iter(&path)
+        .map_err(to_error)?
+        .map(|item| item.map_err(to_error))
+        .collect()
+}
+
+fn parse_file(path: &Path) -> Result<Value, RenditError> {
+    let contents =
+        fs::read_to_string(path).map_err(|e| RenditError::ContextFile(path.to_path_buf(), e))?;
+
+    let format = ContextFormat::from_path(path);
+
+    // `contents.into()` is not the way to do it as some engines did not recognize the JSON structure.
+    format
+        .parse(&contents)
+        .map_err(|(source, location)| RenditError::ContextParse {
+            path: path.to_path_buf(),
+            format: format.name(),
+            location,
+            source,
+        })
+}
+
+fn parse_dir(dir: &Path) -> Result<Value, RenditError> {
+    let mut entries = fs::read_dir(dir)
+        .and_then(|entries| entries.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| RenditError::ContextFile(dir.to_path_buf(), e))?;
+
+    // Sorted, so that files of the same name are merged in a stable order (`site.json` before `site.yaml`).
+    entries.sort_by_key(|entry| entry.file_name());
+
+    let mut object = Value::Object(Default::default());
+
+    for entry in entries {
+        let path = entry.path();
+
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+
+        let (key, value) = match entry.file_type() {
+            Ok(file_type) if file_type.is_dir() => (entry.file_name(), parse_dir(&path)?),
+            _ if path.is_file() && ContextFormat::from_extension(&path).is_some() => {
+                let key = path.file_stem().unwrap_or_default().to_owned();
+                log::debug!("Context file: \"{}\"", path.to_string_lossy());
+                (key, parse_file(&path)?)
+            }
+            _ => {
+                log::debug!("Skipping context file: \"{}\"", path.to_string_lossy());
+                continue;
+            }
+        };
+
+        merge_values(
+            &mut object,
+            serde_json::json!({ key.to_string_lossy(): value }),
+            ArrayMerge::Replace,
+        );
+    }
+    Ok(object)
+}
+
+/// The context used for rendering, along with the file it was loaded from (if any).
+/// A context that was merged from several files keeps the path of the last one.
+#[derive(Clone, Debug)]
+pub struct ContextData {
+    pub context: serde_json::Value,
+    pub file_path: Option<AbsolutePath>,
+}
+
+impl ContextData {
+    /// Loads and parses a context file, in the format of its extension (see [`ContextFormat`]).
+    ///
+    /// A directory is loaded as a nested object, see [`ContextData::from_dir`].
+    pub fn from_file<P: Into<AbsolutePath>>(path: P) -> Result<Self, RenditError> {
+        let path = path.into();
+
+        if path.is_dir() {
+            return Self::from_dir(path);
+        }
+
+        Ok(ContextData {
+            context: parse_file(&path)?,
+            file_path: Some(path),
+        })
+    }
+
+    /// Loads every context file under a directory into a nested object, keyed by the directory name, then by each
+    /// subdirectory and file name (without the extension): `data/users/alice.json` is available as `data.users.alice`.
+    ///
+    /// Files of unknown extensions and hidden files are skipped. A file and a directory of the same name are merged.
+    pub fn from_dir<P: Into<AbsolutePath>>(path: P) -> Result<Self, RenditError> {
+        let path = path.into();
+        let name = path.file_name().ok_or(RenditError::EmptyPath)?;
+
+        Ok(ContextData {
+            context: serde_json::json!({ name.to_string_lossy(): parse_dir(&path)? }),
+            file_path: Some(path),
+        })
+    }
+
+    /// Loads the context files and deep merges them in order, later files overriding earlier ones.
+    pub fn from_files<I, P>(paths: I, arrays: ArrayMerge) -> Result<Self, RenditError>
+    where
+        I: IntoIterator<Item = P>,
+        P: Into<AbsolutePath>,
+    {
+        let mut merged: Option<ContextData> = None;
+        // The last file to set each top-level key, for debugging layered contexts.
+        let mut sources = BTreeMap::new();
+
+        for path in paths {
+            let layer = ContextData::from_file(path)?;
+
+            if let (Value::Object(map), Some(path)) = (&layer.context, &layer.file_path) {
+                for key in map.keys() {
+                    sources.insert(key.to_owned(), path.to_string());
+                }
+            }
+
+            merged = Some(match merged {
+                Some(merged) => merged.merge(layer, arrays),
+                None => layer,
+            });
+        }
+
+        for (key, path) in &sources {
+            log::debug!("Context key `{key}` from \"{path}\"");
+        }
+
+        Ok(merged.unwrap_or_default())
+    }
+
+    /// Sets the value at a dotted `path`. See [`set_path`].
+    pub fn set(&mut self, path: &str, value: Value) -> Result<(), RenditError> {
+        set_path(&mut self.context, path, value)
+    }
+
+    /// Deep merges `other` over this context. See [`merge_values`].
+    pub fn merge(mut self, other: ContextData, arrays: ArrayMerge) -> Self {
+        merge_values(&mut self.context, other.context, arrays);
+
+        ContextData {
+            context: self.context,
+            file_path: other.file_path.or(self.file_path),
+        }
+    }
+}
+
+impl Default for ContextData {
+    fn default() -> Self {
+        serde_json::Value::Object(Default::default()).into()
+    }
+}
+
+impl From<serde_json::Value> for ContextData {
+    fn from(context: serde_json::Value) -> Self {
+        ContextData {
+            context,
+            file_path: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renditlib::test_util::TempDir;
+    use serde_json::json;
+
+    #[test]
+    fn infers_csv_field_types() {
+        assert_eq!(infer_value("42"), json!(42));
+        assert_eq!(infer_value("-7"), json!(-7));
+        assert_eq!(infer_value("1.5"), json!(1.5));
+        assert_eq!(infer_value("TRUE"), json!(true));
+        assert_eq!(infer_value("false"), json!(false));
+        assert_eq!(infer_value("0"), json!(0));
+        assert_eq!(infer_value("0.25"), json!(0.25));
+        assert_eq!(infer_value("hello"), json!("hello"));
+        assert_eq!(infer_value(""), json!(""));
+    }
+
+    #[test]
+    fn keeps_leading_zeros_as_strings() {
+        assert_eq!(infer_value("01234"), json!("01234"));
+        assert_eq!(infer_value("-007"), json!("-007"));
+        assert_eq!(infer_value("+15551234"), json!("+15551234"));
+    }
+
+    #[test]
+    fn converts_every_decimal() {
+        // A column of prices has the same type on every row.
+        assert_eq!(infer_value("10.5"), json!(10.5));
+        assert_eq!(infer_value("10.50"), json!(10.5));
+        assert_eq!(infer_value("1.0"), json!(1.0));
+        assert_eq!(infer_value("-0.250"), json!(-0.25));
+        assert_eq!(infer_value("0.1000000000000000000001"), json!(0.1));
+
+        for field in [
+            "1.", ".5", "1.2.3", "1e3", "1,5", "01.5", "- 1", "NaN", "inf",
+        ] {
+            assert_eq!(infer_value(field), json!(field), "{field}");
+        }
+    }
+
+    #[test]
+    fn keeps_integers_beyond_64_bits_as_strings() {
+        assert_eq!(
+            infer_value("18446744073709551615"),
+            json!(18446744073709551615u64)
+        );
+        assert_eq!(
+            infer_value("-9223372036854775808"),
+            json!(-9223372036854775808i64)
+        );
+        assert_eq!(
+            infer_value("12345678901234567890123"),
+            json!("12345678901234567890123")
+        );
+    }
+
+    #[test]
+    fn parses_rows_by_header() {
+        let csv = "id,acct,zip,name,price\n1,12345678901234567890,01234,\"Doe, Jane\",10.50\n";
+
+        assert_eq!(
+            ContextFormat::Csv.parse(csv).unwrap(),
+            json!([{ "id": 1, "acct": 12345678901234567890u64, "zip": "01234", "name": "Doe, Jane", "price": 10.5 }])
+        );
+        assert_eq!(
+            ContextFormat::Tsv.parse("a\tb\nx y\t2\n").unwrap(),
+            json!([{ "a": "x y", "b": 2 }])
+        );
+    }
+
+    #[test]
+    fn keys_directories_by_their_name() {
+        let dir = TempDir::new("context-dir");
+        fs::create_dir_all(dir.join("data/users")).unwrap();
+        fs::write(dir.join("data/site.json"), r#"{ "title": "Site" }"#).unwrap();
+        fs::write(
+            dir.join("data/users/alice.yaml"),
+            "email: alice@example.com",
+        )
+        .unwrap();
+        fs::write(dir.join("data/.hidden.json"), "{}").unwrap();
+
+        assert_eq!(
+            ContextData::from_dir(dir.join("data")).unwrap().context,
+            json!({ "data": { "site": { "title": "Site" }, "users": { "alice": { "email": "alice@example.com" } } } })
+        );
+    }
+
+    #[test]
+    fn reports_the_location_of_parse_errors() {
+        let dir = TempDir::new("context-parse");
+
+        for (file, contents, location) in [
+            ("bad.json", "{\n  \"a\": 1,\n}", "line 3, column 1"),
+            ("bad.yaml", "a: 1\nb: [1, 2\n", "line 3, column 1"),
+            ("bad.toml", "a = 1\nb = \n", "line 2, column 5"),
+            ("bad.json5", "{\n  a: 1,\n  b: ,\n}", "line 3, column 6"),
+        ] {
+            let path = dir.join(file);
+            fs::write(&path, contents).unwrap();
+
+            let error = ContextData::from_file(&path).unwrap_err();
+            assert!(
+                matches!(
+                    error,
+                    RenditError::ContextParse {
+                        location: Some(_),
+                        ..
+                    }
+                ),
+                "{file}"
+            );
+            assert!(
+                error
+                    .to_string()
+                    .ends_with(&format!("from file \"{}\" at {location}", path.display())),
+                "{error}"
+            );
+        }
+    }
+
+    #[test]
+    fn merges_nested_objects_and_arrays() {
+        let base = json!({
+            "site": { "title": "Site", "tags": ["a"], "nav": [{ "name": "Home" }] },
+            "list": [1, 2],
+        });
+        let overlay = json!({
+            "site": { "tags": ["b"], "nav": [{ "url": "/" }], "lang": "en" },
+            "list": [3],
+        });
+
+        let mut replaced = base.clone();
+        merge_values(&mut replaced, overlay.clone(), ArrayMerge::Replace);
+        assert_eq!(
+            replaced,
+            json!({
+                "site": { "title": "Site", "tags": ["b"], "nav": [{ "url": "/" }], "lang": "en" },
+                "list": [3],
+            })
+        );
+
+        let mut appended = base;
+        merge_values(&mut appended, overlay, ArrayMerge::Append);
+        assert_eq!(
+            appended,
+            json!({
+                "site": { "title": "Site", "tags": ["a", "b"], "nav": [{ "name": "Home" }, { "url": "/" }], "lang": "en" },
+                "list": [1, 2, 3],
+            })
+        );
+
+        // Values of another type replace each other, whatever the policy.
+        let mut value = json!({ "a": [1], "b": { "c": 1 } });
+        merge_values(
+            &mut value,
+            json!({ "a": { "x": 1 }, "b": "text" }),
+            ArrayMerge::Append,
+        );
+        assert_eq!(value, json!({ "a": { "x": 1 }, "b": "text" }));
+    }
+
+    #[test]
+    fn rejects_rows_of_another_length() {
+        assert!(ContextFormat::Csv.parse("a,b\n1,2\n3\n").is_err());
+    }
+
+    #[test]
+    fn parses_paths() {
+        let keys = |path| {
+            parse_path(path).map(|segments| {
+                segments
+                    .into_iter()
+                    .map(|segment| match segment {
+                        Segment::Key(key) => key.to_owned(),
+                        Segment::Index(index) => format!("[{index}]"),
+                    })
+                    .collect::<Vec<_>>()
+            })
+        };
+
+        assert_eq!(keys("a.b").unwrap(), ["a", "b"]);
+        assert_eq!(keys("a.b[0][1].c").unwrap(), ["a", "b", "[0]", "[1]", "c"]);
+        assert_eq!(keys("a.0.c").unwrap(), ["a", "[0]", "c"]);
+        assert_eq!(keys("a-b.c_d").unwrap(), ["a-b", "c_d"]);
+        // Keys aren't quoted, so a quoted key is a bad index.
+        assert!(keys(r#"a["b"]"#).is_err());
+        assert!(keys("a[x]").is_err());
+        assert!(keys("a[0").is_err());
+        assert!(keys("a..b").is_err());
+        assert!(keys("").is_err());
+        assert!(keys("a[18446744073709551616]").is_err());
+    }
+
+    #[test]
+    fn sets_paths() {
+        let mut context = json!({ "a": { "keep": true }, "list": [1] });
+
+        set_path(&mut context, "a.b", json!("x=y")).unwrap();
+        set_path(&mut context, "list[2]", json!(3)).unwrap();
+        set_path(&mut context, "servers[0].host", json!("localhost")).unwrap();
+        set_path(&mut context, "a.0", json!("key")).unwrap();
+
+        assert_eq!(
+            context,
+            json!({
+                "a": { "keep": true, "b": "x=y", "0": "key" },
+                "list": [1, null, 3],
+                "servers": [{ "host": "localhost" }],
+            })
+        );
+        assert_eq!(
+            get_path(&context, "servers.0.host"),
+            Some(&json!("localhost"))
+        );
+        assert_eq!(get_path(&context, "list[5]"), None);
+    }
+
+    #[test]
+    fn rejects_paths_that_conflict_with_the_context() {
+        let mut context = json!({ "name": "x", "list": [], "flag": true });
+
+        for path in ["name.first", "list.key", "flag[0]"] {
+            assert!(
+                matches!(
+                    set_path(&mut context, path, json!(1)),
+                    Err(RenditError::ContextSet { .. })
+                ),
+                "{path}"
+            );
+        }
+    }
+
+    #[test]
+    fn limits_array_padding() {
+        let mut context = json!({});
+
+        set_path(&mut context, "a[1000]", json!(1)).unwrap();
+        assert_eq!(context["a"].as_array().unwrap().len(), 1001);
+
+        for path in ["a.4000000000", "b[18446744073709551615]", "a[2002]"] {
+            assert!(
+                matches!(
+                    set_path(&mut context, path, json!(1)),
+                    Err(RenditError::ContextSet { .. })
+                ),
+                "{path}"
+            );
+        }
+    }
+
+    #[test]
+    fn nests_env_vars() {
+        let vars = [
+            ("RENDIT_DB__HOST", "localhost"),
+            ("RENDIT_DB__PORT", "5432"),
+            ("RENDIT_NAME", "first"),
+            ("RENDIT_NAME", "last"),
+            ("RENDIT_", "empty"),
+            ("RENDIT_NAME__FIRST", "conflict"),
+            ("OTHER", "skipped"),
+        ]
+        .map(|(key, value)| (key.to_owned(), value.to_owned()));
+
+        assert_eq!(
+            env_object(vars, "RENDIT_"),
+            json!({ "DB": { "HOST": "localhost", "PORT": "5432" }, "NAME": "last" })
+        );
+    }
+
+    #[test]
+    fn reads_env_files() {
+        let dir = TempDir::new("context-env");
+        fs::write(
+            dir.join(".env"),
+            "# comment\nRENDIT_A=1\nexport RENDIT_B=\"two words\"\nRENDIT_C='$RENDIT_A'\n",
+        )
+        .unwrap();
+        fs::write(dir.join("bad.env"), "RENDIT_A=\"unclosed\n").unwrap();
+
+        assert_eq!(
+            read_env_file(dir.join(".env")).unwrap(),
+            [
+                ("RENDIT_A", "1"),
+                ("RENDIT_B", "two words"),
+                ("RENDIT_C", "$RENDIT_A")
+            ]
+            .map(|(key, value)| (key.to_owned(), value.to_owned()))
+        );
+        assert!(matches!(
+            read_env_file(dir.join("bad.env")),
+            Err(RenditError::ContextParse {
+                format: "dotenv",
+                ..
+            })
+        ));
+        assert!(matches!(
+            read_env_file(dir.join("missing.env")),
+            Err(RenditError::ContextFile(..))
+        ));
+    }
+}
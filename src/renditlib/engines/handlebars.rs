@@ -0,0 +1,305 @@
+use handlebars::Handlebars;
+use regex::Regex;
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+    fs,
+    path::{Component, Path, PathBuf},
+};
+
+use super::{CompiledTemplate, EngineInfo, Renderer};
+use crate::renditlib::{error::RenditError, render::RenderOptions, template::TemplateData};
+
+/// Renders templates with [Handlebars](https://handlebarsjs.com/guide/).
+///
+/// A partial is an `.hbs` file under the template's directory (the working directory in STDIN mode),
+/// named by its relative path without the extension (`{{> header}}`, `{{#> layouts/base}}`).
+/// Files under a `partials/` folder may also be referenced without the folder (`partials/footer.hbs` as `{{> footer}}`).
+/// The include directories of [`RenderOptions`] are searched the same way, after the template's directory.
+/// Only the partials that are referenced are looked up.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct HandlebarsRenderer;
+
+impl Renderer for HandlebarsRenderer {
+    fn info(&self) -> EngineInfo {
+        super::HANDLEBARS
+    }
+
+    fn render(
+        &self,
+        template: &TemplateData,
+        context: &serde_json::Value,
+        options: &RenderOptions,
+    ) -> Result<String, RenditError> {
+        self.compile(template, options)?.render(context)
+    }
+
+    fn compile<'a>(
+        &'a self,
+        template: &TemplateData,
+        options: &RenderOptions,
+    ) -> Result<Box<dyn CompiledTemplate + 'a>, RenditError> {
+        let (template_dir, template_name) = match &template.file_path {
+            Some(template_file) => (
+                template_file
+                    .parent()
+                    .ok_or(RenditError::EmptyPath)?
+                    .to_path_buf(),
+                template_file
+                    .file_name()
+                    .ok_or(RenditError::EmptyPath)?
+                    .to_string_lossy()
+                    .into_owned(),
+            ),
+            None => (
+                std::env::current_dir().map_err(RenditError::CurrentDir)?,
+                String::from("__in_memory__"),
+            ),
+        };
+
+        let partials_dirs = options.template_dirs(template_dir);
+
+        log::debug!("Handlebars partials paths: {partials_dirs:?}");
+
+        let mut handlebars = Handlebars::new();
+
+        if let Some(strict) = options.strict {
+            handlebars.set_strict_mode(strict);
+        }
+
+        handlebars
+            .register_template_string(&template_name, template.contents.as_str())
+            .map_err(|e| RenditError::engine("Handlebars is unable to parse the template.", e))?;
+
+        let referrer = match &template.file_path {
+            Some(template_file) => template_file.to_string(),
+            None => String::from("STDIN"),
+        };
+
+        register_partials(
+            &mut handlebars,
+            &partials_dirs,
+            referrer,
+            &template.contents,
+            options,
+        )?;
+
+        Ok(Box::new(CompiledHandlebars {
+            handlebars,
+            template_name,
+        }))
+    }
+}
+
+/// A Handlebars registry with the main template and its partials registered.
+struct CompiledHandlebars {
+    handlebars: Handlebars<'static>,
+    template_name: String,
+}
+
+impl CompiledTemplate for CompiledHandlebars {
+    fn render(&self, context: &serde_json::Value) -> Result<String, RenditError> {
+        self.handlebars
+            .render(&self.template_name, context)
+            .map_err(|e| RenditError::engine("Handlebars is unable to render the template.", e))
+    }
+}
+
+/// Registers the partials that are reachable from the template, and fails on references to missing ones.
+///
+/// Handlebars itself renders a missing partial as empty, which hides typos in partial names.
+fn register_partials(
+    handlebars: &mut Handlebars,
+    partials_dirs: &[PathBuf],
+    referrer: String,
+    contents: &str,
+    options: &RenderOptions,
+) -> Result<(), RenditError> {
+    // The file of each partial name, or `None` if it is missing.
+    let mut found: HashMap<String, Option<PathBuf>> = HashMap::new();
+
+    let reference_re = Regex::new(r#"\{\{~?(?P<block>#)?>\s*["'\[]?(?P<name>[^\s"'\]}~()]+)"#)
+        .expect("Bad regex pattern.");
+    let inline_re =
+        Regex::new(r#"\{\{~?#\*inline\s+["'](?P<name>[^"']+)["']"#).expect("Bad regex pattern.");
+    let text_re = text_regex();
+
+    // Inline partials may be defined by one file and used by another, as with layouts.
+    let mut inline = HashSet::new();
+    // (partial, referencing file) of non-block partials, which have no fallback content.
+    let mut references = Vec::new();
+
+    let mut visited = HashSet::new();
+    let mut queue = vec![(referrer, contents.to_owned())];
+
+    while let Some((file_name, source)) = queue.pop() {
+        let source = strip_text(&text_re, &source);
+
+        inline.extend(
+            inline_re
+                .captures_iter(&source)
+                .map(|c| c["name"].to_owned()),
+        );
+
+        for cap in reference_re.captures_iter(&source) {
+            let name = &cap["name"];
+
+            if name.starts_with('@') {
+                continue;
+            }
+
+            if cap.name("block").is_none() {
+                references.push((name.to_owned(), file_name.clone()));
+            }
+
+            let Some(path) = found
+                .entry(name.to_owned())
+                .or_insert_with(|| find_partial(partials_dirs, name))
+                .as_ref()
+            else {
+                continue;
+            };
+
+            if !visited.insert(name.to_owned()) {
+                continue;
+            }
+
+            log::debug!(
+                "Handlebars: Partial `{name}` is \"{}\"",
+                path.to_string_lossy()
+            );
+
+            options.restrict(path.as_path())?;
+
+            let partial = fs::read_to_string(path)
+                .map_err(|e| RenditError::TemplateFile(path.to_owned(), e))?;
+
+            handlebars.register_partial(name, &partial).map_err(|e| {
+                RenditError::engine(
+                    format!("Handlebars is unable to parse the partial `{name}`."),
+                    e,
+                )
+            })?;
+
+            queue.push((path.to_string_lossy().into_owned(), partial));
+        }
+    }
+
+    match references
+        .into_iter()
+        .find(|(name, _)| found.get(name).is_some_and(Option::is_none) && !inline.contains(name))
+    {
+        Some((name, file_name)) => Err(RenditError::engine(
+            "Handlebars is unable to render the template.",
+            format!("Partial `{name}` is not found (referenced by \"{file_name}\")"),
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Matches the comments and the escaped mustaches (`\{{> name}}`) of a template, which are not references.
+/// An escaped backslash (`\\{{> name}}`) is matched on its own, so that the mustache after it is kept.
+fn text_regex() -> Regex {
+    Regex::new(r"(?s)\\\\|\\\{\{.*?\}\}|\{\{~?!--.*?--~?\}\}|\{\{~?!.*?\}\}")
+        .expect("Bad regex pattern.")
+}
+
+/// Removes the comments and escaped mustaches matched by [`text_regex`] from a template.
+fn strip_text<'a>(text_re: &Regex, source: &'a str) -> Cow<'a, str> {
+    text_re.replace_all(source, |cap: &regex::Captures| match &cap[0] {
+        r"\\" => r"\\".to_owned(),
+        _ => String::new(),
+    })
+}
+
+/// Finds the file of the partial `name` in the first directory that has it.
+///
+/// Within a directory, a partial's own path takes precedence over a shortened `partials/` name.
+fn find_partial(partials_dirs: &[PathBuf], name: &str) -> Option<PathBuf> {
+    // Names are relative paths below the directories, such as `layouts/base`.
+    let relative = Path::new(name);
+    if !relative
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)))
+    {
+        return None;
+    }
+
+    partials_dirs.iter().find_map(|dir| {
+        [dir.join(relative), dir.join("partials").join(relative)]
+            .into_iter()
+            .flat_map(|path| {
+                // Appended rather than set, as a name may have dots of its own (`header.v2`).
+                super::HANDLEBARS.extensions.iter().map(move |ext| {
+                    let mut file = path.clone().into_os_string();
+                    file.push(format!(".{ext}"));
+                    PathBuf::from(file)
+                })
+            })
+            .find(|path| path.is_file())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renditlib::test_util::TempDir;
+
+    #[test]
+    fn finds_referenced_partials() {
+        let dir = TempDir::new("handlebars-partials");
+        for file in [
+            "one/header.hbs",
+            "one/partials/header.hbs",
+            "one/partials/footer.hbs",
+            "one/layouts/base.handlebars",
+            "one/header.v2.hbs",
+            "two/footer.hbs",
+            "two/nav.hbs",
+        ] {
+            fs::create_dir_all(dir.join(file).parent().unwrap()).unwrap();
+            fs::write(dir.join(file), "").unwrap();
+        }
+
+        let dirs = [dir.join("one"), dir.join("two")];
+        let find = |name| {
+            find_partial(&dirs, name).map(|path| path.strip_prefix(&dir).unwrap().to_owned())
+        };
+
+        assert_eq!(find("header"), Some("one/header.hbs".into()));
+        assert_eq!(find("footer"), Some("one/partials/footer.hbs".into()));
+        assert_eq!(
+            find("layouts/base"),
+            Some("one/layouts/base.handlebars".into())
+        );
+        assert_eq!(find("header.v2"), Some("one/header.v2.hbs".into()));
+        assert_eq!(find("nav"), Some("two/nav.hbs".into()));
+        assert_eq!(find("missing"), None);
+        assert_eq!(find("../one/header"), None);
+        assert_eq!(find("/etc/passwd"), None);
+    }
+
+    #[test]
+    fn strips_comments_and_escaped_mustaches() {
+        let text_re = text_regex();
+        let strip = |source| strip_text(&text_re, source).into_owned();
+
+        assert_eq!(strip("ok{{!-- {{> old_header}} --}}"), "ok");
+        assert_eq!(strip("a{{~!-- }} --~}}b{{! note }}c"), "abc");
+        assert_eq!(strip(r"\{{> literal}} {{> real}}"), " {{> real}}");
+        assert_eq!(strip(r"\\{{> real}}"), r"\\{{> real}}");
+    }
+
+    #[test]
+    fn ignores_partials_of_comments_and_escaped_mustaches() {
+        let template = TemplateData {
+            contents: std::rc::Rc::new(r"ok{{!-- {{> old_header}} --}} \{{> literal}}".to_owned()),
+            file_path: None,
+        };
+        let rendered = HandlebarsRenderer
+            .render(&template, &serde_json::json!({}), &RenderOptions::default())
+            .unwrap();
+
+        assert_eq!(rendered, "ok {{> literal}}");
+    }
+}
@@ -0,0 +1,212 @@
+use liquid::partials::{LazyCompiler, PartialSource};
+use std::{
+    borrow::Cow,
+    fs,
+    path::{Component, Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use super::{CompiledTemplate, EngineInfo, Renderer};
+use crate::renditlib::{
+    error::RenditError, path::AbsolutePath, render::RenderOptions, template::TemplateData,
+};
+
+/// Renders templates with [Liquid](https://github.com/Shopify/liquid/wiki/Liquid-for-Designers).
+///
+/// Partials of `{% include 'header' %}` and `{% render 'header' %}` are loaded from `header`, `header.liquid`
+/// or `header.liq` in the template's directory (the working directory in STDIN mode),
+/// and then from the include directories of [`RenderOptions`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LiquidRenderer;
+
+impl Renderer for LiquidRenderer {
+    fn info(&self) -> EngineInfo {
+        super::LIQUID
+    }
+
+    fn render(
+        &self,
+        template: &TemplateData,
+        context: &serde_json::Value,
+        options: &RenderOptions,
+    ) -> Result<String, RenditError> {
+        self.compile(template, options)?.render(context)
+    }
+
+    fn compile<'a>(
+        &'a self,
+        template: &TemplateData,
+        options: &RenderOptions,
+    ) -> Result<Box<dyn CompiledTemplate + 'a>, RenditError> {
+        let template_dir = match &template.file_path {
+            Some(template_file) => template_file
+                .parent()
+                .ok_or(RenditError::EmptyPath)?
+                .to_path_buf(),
+            None => std::env::current_dir().map_err(RenditError::CurrentDir)?,
+        };
+
+        if options.strict == Some(false) {
+            return Err(RenditError::LenientNotSupported(super::LIQUID.name));
+        }
+
+        let dirs = options.template_dirs(template_dir);
+
+        log::debug!("Liquid partials paths: {dirs:?}");
+
+        let partials = PartialDirs::new(dirs, options.root.clone());
+        let rejected = partials.rejected.clone();
+
+        let parsed = liquid::ParserBuilder::with_stdlib()
+            .partials(LazyCompiler::new(partials))
+            .build()
+            .map_err(|e| RenditError::engine("Liquid is unable to build the parser.", e))?
+            .parse(&template.contents)
+            .map_err(|e| {
+                take_rejected(&rejected).unwrap_or_else(|| {
+                    RenditError::engine("Liquid is unable to parse the template.", e)
+                })
+            })?;
+
+        Ok(Box::new(CompiledLiquid { parsed, rejected }))
+    }
+}
+
+struct CompiledLiquid {
+    parsed: liquid::Template,
+    rejected: Arc<Mutex<Option<RenditError>>>,
+}
+
+impl CompiledTemplate for CompiledLiquid {
+    fn render(&self, context: &serde_json::Value) -> Result<String, RenditError> {
+        let globals = liquid::to_object(context)
+            .map_err(|e| RenditError::engine("Liquid rejected Context object.", e))?;
+
+        self.parsed.render(&globals).map_err(|e| {
+            take_rejected(&self.rejected).unwrap_or_else(|| {
+                RenditError::engine("Liquid is unable to render the template.", e)
+            })
+        })
+    }
+}
+
+/// Takes the error of a partial that was rejected for being outside of the root directory,
+/// which Liquid itself only reports as missing.
+fn take_rejected(rejected: &Mutex<Option<RenditError>>) -> Option<RenditError> {
+    rejected.lock().ok()?.take()
+}
+
+const PARTIAL_EXTENSIONS: &[&str] = &["liquid", "liq"];
+
+/// Loads partials from a list of directories, the first match wins.
+#[derive(Debug)]
+struct PartialDirs {
+    dirs: Vec<PathBuf>,
+    /// The partials found directly under the directories, for listing in errors.
+    names: Vec<String>,
+    /// Partials must not resolve outside of this directory.
+    root: Option<AbsolutePath>,
+    /// The last partial that resolved outside of the root directory.
+    rejected: Arc<Mutex<Option<RenditError>>>,
+}
+
+impl PartialDirs {
+    fn new(dirs: Vec<PathBuf>, root: Option<AbsolutePath>) -> Self {
+        let mut names = Vec::new();
+
+        for entry in dirs
+            .iter()
+            .filter_map(|dir| fs::read_dir(dir).ok())
+            .flatten()
+            .flatten()
+        {
+            let path = entry.path();
+            let is_partial = path
+                .extension()
+                .is_some_and(|ext| PARTIAL_EXTENSIONS.contains(&&*ext.to_string_lossy()));
+
+            if is_partial && path.is_file() {
+                if let Some(stem) = path.file_stem() {
+                    names.push(stem.to_string_lossy().into_owned());
+                }
+            }
+        }
+        names.sort_unstable();
+        names.dedup();
+
+        PartialDirs {
+            dirs,
+            names,
+            root,
+            rejected: Default::default(),
+        }
+    }
+
+    fn find(&self, name: &str) -> Option<PathBuf> {
+        // Names are relative paths below the directories, such as `layouts/base`.
+        if !Path::new(name)
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+        {
+            return None;
+        }
+
+        self.dirs
+            .iter()
+            .find_map(|dir| {
+                std::iter::once(dir.join(name))
+                    .chain(
+                        PARTIAL_EXTENSIONS
+                            .iter()
+                            .map(|ext| dir.join(format!("{name}.{ext}"))),
+                    )
+                    .find(|path| path.is_file())
+            })
+            .filter(|path| self.is_within_root(path))
+    }
+
+    fn is_within_root(&self, path: &Path) -> bool {
+        let Some(root) = &self.root else {
+            return true;
+        };
+
+        match AbsolutePath::from(path).restrict_to(root) {
+            Ok(_) => true,
+            Err(e) => {
+                if let Ok(mut rejected) = self.rejected.lock() {
+                    *rejected = Some(e);
+                }
+                false
+            }
+        }
+    }
+}
+
+impl PartialSource for PartialDirs {
+    fn contains(&self, name: &str) -> bool {
+        self.find(name).is_some()
+    }
+
+    fn names(&self) -> Vec<&str> {
+        self.names.iter().map(String::as_str).collect()
+    }
+
+    fn try_get<'a>(&'a self, name: &str) -> Option<Cow<'a, str>> {
+        let path = self.find(name)?;
+
+        log::debug!("Liquid: Partial `{name}` is \"{}\"", path.to_string_lossy());
+
+        read_partial(&path).map(Cow::Owned)
+    }
+}
+
+fn read_partial(path: &Path) -> Option<String> {
+    fs::read_to_string(path)
+        .map_err(|e| {
+            log::error!(
+                "Liquid: Unable to read partial \"{}\": {e}",
+                path.to_string_lossy()
+            )
+        })
+        .ok()
+}
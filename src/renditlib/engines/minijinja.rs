@@ -0,0 +1,143 @@
+use minijinja::{path_loader, Environment, ErrorKind, UndefinedBehavior};
+use std::{borrow::Cow, rc::Rc};
+
+use super::{CompiledTemplate, EngineInfo, Renderer};
+use crate::renditlib::{
+    error::RenditError,
+    path::AbsolutePath,
+    render::RenderOptions,
+    template::{TemplateData, TemplateExtension},
+};
+
+/// Renders Jinja2 compatible templates with [MiniJinja](https://docs.rs/minijinja/latest/minijinja/syntax/index.html).
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MiniJinjaRenderer;
+
+impl Renderer for MiniJinjaRenderer {
+    fn info(&self) -> EngineInfo {
+        super::MINIJINJA
+    }
+
+    fn render(
+        &self,
+        template: &TemplateData,
+        context: &serde_json::Value,
+        options: &RenderOptions,
+    ) -> Result<String, RenditError> {
+        self.compile(template, options)?.render(context)
+    }
+
+    fn compile<'a>(
+        &'a self,
+        template: &TemplateData,
+        options: &RenderOptions,
+    ) -> Result<Box<dyn CompiledTemplate + 'a>, RenditError> {
+        // `include`, `extends` and `import` are resolved relative to the template's directory,
+        // or to the working directory in STDIN mode, and then to the include directories.
+        let templates_home_dir = match &template.file_path {
+            Some(template_file) => template_file
+                .parent()
+                .ok_or(RenditError::EmptyPath)?
+                .to_path_buf(),
+            None => std::env::current_dir().map_err(RenditError::CurrentDir)?,
+        };
+
+        let loaders: Vec<_> = options
+            .template_dirs(templates_home_dir)
+            .into_iter()
+            .map(|dir| (path_loader(dir.clone()), dir))
+            .collect();
+
+        log::debug!(
+            "MiniJinja templates paths: {:?}",
+            loaders.iter().map(|(_, dir)| dir).collect::<Vec<_>>()
+        );
+
+        let root = options.root.clone();
+        let mut env = Environment::new();
+
+        // The first directory that has the template wins.
+        env.set_loader(move |name| {
+            for (loader, dir) in &loaders {
+                let Some(source) = loader(name)? else {
+                    continue;
+                };
+
+                let path: AbsolutePath = dir.join(name).into();
+                log::debug!("MiniJinja: `{name}` is \"{path}\"");
+
+                if let Some(root) = &root {
+                    path.restrict_to(root).map_err(|e| {
+                        minijinja::Error::new(ErrorKind::InvalidOperation, e.to_string())
+                    })?;
+                }
+                return Ok(Some(source));
+            }
+            Ok(None)
+        });
+
+        // The output ends the way the template does, as with the other engines.
+        env.set_keep_trailing_newline(true);
+
+        // Lenient mode also lets attributes of missing variables render as empty (`{{ user.name }}`).
+        match options.strict {
+            Some(true) => env.set_undefined_behavior(UndefinedBehavior::Strict),
+            Some(false) => env.set_undefined_behavior(UndefinedBehavior::Chainable),
+            None => {}
+        }
+
+        // Auto escaping is decided by the template name, the same way it is for files loaded from disk
+        // (`my_template.html.j2` is escaped as HTML). Defaults to `.html`.
+        let template_name: Cow<str> = if let TemplateExtension::Force(ext) = &options.extension {
+            log::debug!("MiniJinja: Forcing extension \"{ext}\"");
+            Cow::Borrowed(ext)
+        } else if let Some(file_name) = template.file_path.as_ref().and_then(|p| p.file_name()) {
+            file_name.to_string_lossy()
+        } else {
+            Cow::Borrowed("html")
+        };
+
+        let in_memory_template = format!("__in_memory__.{template_name}");
+
+        env.add_template_owned(in_memory_template.clone(), template.contents.to_string())
+            .map_err(|e| RenditError::engine("MiniJinja is unable to parse the template.", e))?;
+
+        Ok(Box::new(CompiledMiniJinja {
+            env,
+            template_name: in_memory_template,
+            contents: template.contents.clone(),
+        }))
+    }
+}
+
+/// A MiniJinja environment with the main template added.
+struct CompiledMiniJinja {
+    env: Environment<'static>,
+    template_name: String,
+    /// The source of the main template, for naming missing variables.
+    contents: Rc<String>,
+}
+
+impl CompiledTemplate for CompiledMiniJinja {
+    fn render(&self, context: &serde_json::Value) -> Result<String, RenditError> {
+        self.env
+            .get_template(&self.template_name)
+            .and_then(|t| t.render(context))
+            .map_err(|e| match (e.kind(), e.name(), e.range()) {
+                // MiniJinja only points at the line, so name the missing variable as well.
+                (ErrorKind::UndefinedError, Some(name), Some(range))
+                    if name == self.template_name =>
+                {
+                    RenditError::engine(
+                        "MiniJinja is unable to render the template.",
+                        format!(
+                            "Variable `{}` is undefined (line {})",
+                            &self.contents[range],
+                            e.line().unwrap_or_default()
+                        ),
+                    )
+                }
+                _ => RenditError::engine("MiniJinja is unable to render the template.", e),
+            })
+    }
+}
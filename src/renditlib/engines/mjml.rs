@@ -0,0 +1,54 @@
+use mrml::prelude::{
+    parser::{local_loader::LocalIncludeLoader, ParserOptions},
+    render::RenderOptions as MjmlRenderOptions,
+};
+
+use super::{EngineInfo, Renderer};
+use crate::renditlib::{error::RenditError, render::RenderOptions, template::TemplateData};
+
+/// Compiles [MJML](https://documentation.mjml.io/) into responsive email HTML.
+///
+/// MJML is not a template engine, and doesn't read the context. It's meant to run as the last stage
+/// of an engine chain (`<!--template tera+mjml-->`), or on its own for `.mjml` files.
+/// `<mj-include>` paths are resolved from the template's directory, or from the working directory in STDIN mode.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MjmlRenderer;
+
+impl Renderer for MjmlRenderer {
+    fn info(&self) -> EngineInfo {
+        super::MJML
+    }
+
+    fn render(
+        &self,
+        template: &TemplateData,
+        _context: &serde_json::Value,
+        _options: &RenderOptions,
+    ) -> Result<String, RenditError> {
+        let include_dir = match &template.file_path {
+            Some(template_file) => template_file
+                .parent()
+                .ok_or(RenditError::EmptyPath)?
+                .to_path_buf(),
+            None => std::env::current_dir().map_err(RenditError::CurrentDir)?,
+        };
+
+        log::debug!("MJML include path: {}", include_dir.to_string_lossy());
+
+        let parser_options = ParserOptions {
+            include_loader: Box::new(LocalIncludeLoader::new(include_dir)),
+        };
+
+        let parsed = mrml::parse_with_options(template.contents.as_str(), &parser_options)
+            .map_err(|e| RenditError::engine("MJML is unable to parse the template.", e))?;
+
+        for warning in &parsed.warnings {
+            log::warn!("MJML: {} (bytes {})", warning.kind, warning.span);
+        }
+
+        parsed
+            .element
+            .render(&MjmlRenderOptions::default())
+            .map_err(|e| RenditError::engine("MJML is unable to render the template.", e))
+    }
+}
@@ -0,0 +1,192 @@
+#[cfg(feature = "engine-handlebars")]
+mod handlebars;
+#[cfg(feature = "engine-liquid")]
+mod liquid;
+#[cfg(feature = "engine-minijinja")]
+mod minijinja;
+#[cfg(feature = "engine-mjml")]
+mod mjml;
+#[cfg(feature = "engine-mustache")]
+mod mustache;
+mod none;
+#[cfg(feature = "engine-subst")]
+mod subst;
+#[cfg(feature = "engine-tera")]
+mod tera;
+
+#[cfg(feature = "engine-handlebars")]
+pub use self::handlebars::HandlebarsRenderer;
+#[cfg(feature = "engine-liquid")]
+pub use self::liquid::LiquidRenderer;
+#[cfg(feature = "engine-minijinja")]
+pub use self::minijinja::MiniJinjaRenderer;
+#[cfg(feature = "engine-mjml")]
+pub use self::mjml::MjmlRenderer;
+#[cfg(feature = "engine-mustache")]
+pub use self::mustache::MustacheRenderer;
+pub use self::none::NoneRenderer;
+#[cfg(feature = "engine-subst")]
+pub use self::subst::SubstRenderer;
+#[cfg(feature = "engine-tera")]
+pub use self::tera::TeraRenderer;
+
+use super::{error::RenditError, render::RenderOptions, template::TemplateData};
+
+/// Describes how an engine is selected: by name, by template file extension or by magic comment.
+///
+/// All values are matched case-insensitively and should be given in lowercase.
+#[derive(Clone, Copy, Debug)]
+pub struct EngineInfo {
+    /// The canonical engine name, as shown in `--engine-list`.
+    pub name: &'static str,
+    /// Alternative names accepted by `--engine`.
+    pub aliases: &'static [&'static str],
+    /// Template file extensions that select the engine (`my_template.html.tera`).
+    /// They are also dropped from the default output file name.
+    pub extensions: &'static [&'static str],
+    /// Keys that select the engine in a magic comment (`<!--template tera-->`).
+    pub magic_keys: &'static [&'static str],
+    /// Runs after the template engine rather than in its place, when selected by file extension.
+    /// A `.mjml` file with a `<!--template tera-->` comment is rendered as `tera+mjml`.
+    pub post_processor: bool,
+    /// Replaces the engine extension in the default output file name (`email.mjml` -> `email.html`).
+    pub output_extension: Option<&'static str>,
+}
+
+impl EngineInfo {
+    /// Returns `true` if `name` is the engine name or one of its aliases.
+    pub fn is_named(&self, name: &str) -> bool {
+        let name = name.to_lowercase();
+        self.name == name || self.aliases.contains(&name.as_str())
+    }
+
+    /// Returns `true` if `key` selects the engine by name, alias, file extension or magic comment.
+    pub(crate) fn is_selected_by(&self, key: &str) -> bool {
+        let key = key.to_lowercase();
+        self.is_named(&key)
+            || self.extensions.contains(&key.as_str())
+            || self.magic_keys.contains(&key.as_str())
+    }
+}
+
+pub(crate) const TERA: EngineInfo = EngineInfo {
+    name: "tera",
+    aliases: &[],
+    extensions: &["tera"],
+    magic_keys: &["tera"],
+    post_processor: false,
+    output_extension: None,
+};
+
+pub(crate) const LIQUID: EngineInfo = EngineInfo {
+    name: "liquid",
+    aliases: &["liq"],
+    extensions: &["liq", "liquid"],
+    magic_keys: &["liq", "liquid"],
+    post_processor: false,
+    output_extension: None,
+};
+
+pub(crate) const HANDLEBARS: EngineInfo = EngineInfo {
+    name: "handlebars",
+    aliases: &["hbs"],
+    extensions: &["hbs", "handlebars"],
+    magic_keys: &["hbs", "handlebars"],
+    post_processor: false,
+    output_extension: None,
+};
+
+pub(crate) const MINIJINJA: EngineInfo = EngineInfo {
+    name: "minijinja",
+    aliases: &["jinja", "j2"],
+    extensions: &["j2"],
+    magic_keys: &["minijinja", "jinja", "j2"],
+    post_processor: false,
+    output_extension: None,
+};
+
+pub(crate) const MUSTACHE: EngineInfo = EngineInfo {
+    name: "mustache",
+    aliases: &[],
+    extensions: &["mustache"],
+    magic_keys: &["mustache"],
+    post_processor: false,
+    output_extension: None,
+};
+
+pub(crate) const SUBST: EngineInfo = EngineInfo {
+    name: "subst",
+    aliases: &["envsubst"],
+    extensions: &["subst"],
+    magic_keys: &["subst", "envsubst"],
+    post_processor: false,
+    output_extension: None,
+};
+
+pub(crate) const MJML: EngineInfo = EngineInfo {
+    name: "mjml",
+    aliases: &[],
+    extensions: &["mjml"],
+    magic_keys: &["mjml"],
+    post_processor: true,
+    output_extension: Some("html"),
+};
+
+/// Built-in engines that may be left out of the build, along with the cargo feature that compiles them in.
+pub(crate) const OPTIONAL_ENGINES: &[(EngineInfo, &str)] = &[
+    (TERA, "engine-tera"),
+    (LIQUID, "engine-liquid"),
+    (HANDLEBARS, "engine-handlebars"),
+    (MINIJINJA, "engine-minijinja"),
+    (MUSTACHE, "engine-mustache"),
+    (SUBST, "engine-subst"),
+    (MJML, "engine-mjml"),
+];
+
+/// A template engine that is able to render a template with a JSON context.
+pub trait Renderer {
+    /// Describes the engine for the [`EngineRegistry`](super::EngineRegistry).
+    fn info(&self) -> EngineInfo;
+
+    /// Renders the template contents with the given context.
+    fn render(
+        &self,
+        template: &TemplateData,
+        context: &serde_json::Value,
+        options: &RenderOptions,
+    ) -> Result<String, RenditError>;
+
+    /// Parses the template once, to be rendered with many contexts.
+    ///
+    /// Engines that parse their templates should override it. By default, the template is kept as is
+    /// and rendered from scratch every time.
+    fn compile<'a>(
+        &'a self,
+        template: &TemplateData,
+        options: &RenderOptions,
+    ) -> Result<Box<dyn CompiledTemplate + 'a>, RenditError> {
+        Ok(Box::new(Deferred {
+            renderer: self,
+            template: template.clone(),
+            options: options.clone(),
+        }))
+    }
+}
+
+/// A template that was parsed by [`Renderer::compile`], ready to be rendered with any context.
+pub trait CompiledTemplate {
+    fn render(&self, context: &serde_json::Value) -> Result<String, RenditError>;
+}
+
+/// The [`CompiledTemplate`] of engines without a parsing step.
+struct Deferred<'a, R: ?Sized> {
+    renderer: &'a R,
+    template: TemplateData,
+    options: RenderOptions,
+}
+
+impl<R: Renderer + ?Sized> CompiledTemplate for Deferred<'_, R> {
+    fn render(&self, context: &serde_json::Value) -> Result<String, RenditError> {
+        self.renderer.render(&self.template, context, &self.options)
+    }
+}
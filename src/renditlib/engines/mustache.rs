@@ -0,0 +1,490 @@
+use serde_json::Value;
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    fs, mem,
+    path::{Component, Path, PathBuf},
+    rc::Rc,
+};
+
+use super::{CompiledTemplate, EngineInfo, Renderer};
+use crate::renditlib::{
+    error::RenditError, path::AbsolutePath, render::RenderOptions, template::TemplateData,
+};
+
+/// Renders logic-less [Mustache](https://mustache.github.io/mustache.5.html) templates.
+///
+/// Follows the core modules of the Mustache spec (without lambdas).
+/// Partials (`{{> header}}`) are loaded from `header.mustache` or `header` in the template's directory,
+/// or in the working directory in STDIN mode, and then in the include directories of [`RenderOptions`].
+/// Partials may include themselves, as long as they are nested no more than 100 levels deep.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MustacheRenderer;
+
+/// How deeply partials may be nested, to stop a partial that includes itself without end.
+const MAX_PARTIAL_DEPTH: usize = 100;
+
+/// The parsed partials, by name and indentation, or `None` for missing ones.
+type Partials = HashMap<(String, String), Option<Rc<Vec<Node>>>>;
+
+impl Renderer for MustacheRenderer {
+    fn info(&self) -> EngineInfo {
+        super::MUSTACHE
+    }
+
+    fn render(
+        &self,
+        template: &TemplateData,
+        context: &serde_json::Value,
+        options: &RenderOptions,
+    ) -> Result<String, RenditError> {
+        self.compile(template, options)?.render(context)
+    }
+
+    fn compile<'a>(
+        &'a self,
+        template: &TemplateData,
+        options: &RenderOptions,
+    ) -> Result<Box<dyn CompiledTemplate + 'a>, RenditError> {
+        let template_dir = match &template.file_path {
+            Some(template_file) => template_file
+                .parent()
+                .ok_or(RenditError::EmptyPath)?
+                .to_path_buf(),
+            None => std::env::current_dir().map_err(RenditError::CurrentDir)?,
+        };
+
+        let partials_dirs = options.template_dirs(template_dir);
+
+        log::debug!("Mustache partials paths: {partials_dirs:?}");
+
+        let nodes = parse(&template.contents)
+            .map_err(|e| RenditError::engine("Mustache is unable to parse the template.", e))?;
+
+        Ok(Box::new(CompiledMustache {
+            nodes,
+            partials_dirs,
+            strict: options.strict.unwrap_or_default(),
+            root: options.root.clone(),
+            partials: RefCell::default(),
+        }))
+    }
+}
+
+/// The parsed main template. Partials are parsed the first time they are rendered, and kept for the next renders.
+struct CompiledMustache {
+    nodes: Vec<Node>,
+    partials_dirs: Vec<PathBuf>,
+    strict: bool,
+    root: Option<AbsolutePath>,
+    partials: RefCell<Partials>,
+}
+
+impl CompiledTemplate for CompiledMustache {
+    fn render(&self, context: &Value) -> Result<String, RenditError> {
+        let mut rendered = String::new();
+        Mustache {
+            partials_dirs: &self.partials_dirs,
+            strict: self.strict,
+            root: self.root.as_ref(),
+            partials: &self.partials,
+        }
+        .render(&self.nodes, &mut vec![context], &mut rendered, 0)?;
+
+        Ok(rendered)
+    }
+}
+
+enum Node {
+    Text(String),
+    Variable {
+        name: String,
+        escape: bool,
+    },
+    Section {
+        name: String,
+        inverted: bool,
+        children: Vec<Node>,
+    },
+    Partial {
+        name: String,
+        indent: String,
+    },
+}
+
+/// Parses the template into a tree of nodes, removing comments, delimiter changes and standalone tag lines.
+fn parse(source: &str) -> Result<Vec<Node>, String> {
+    let mut otag = String::from("{{");
+    let mut ctag = String::from("}}");
+
+    let mut pos = 0;
+    let mut nodes = Vec::new();
+    // Open sections: (name, inverted, nodes of the parent)
+    let mut sections: Vec<(String, bool, Vec<Node>)> = Vec::new();
+
+    while let Some(offset) = source[pos..].find(&otag) {
+        let tag_start = pos + offset;
+        let content_start = tag_start + otag.len();
+
+        let sigil = source[content_start..].chars().next();
+        let (sigil, close) = match sigil {
+            Some('{') => ('{', format!("}}{ctag}")),
+            Some('=') => ('=', format!("={ctag}")),
+            Some(c @ ('#' | '^' | '/' | '!' | '>' | '&')) => (c, ctag.clone()),
+            _ => (' ', ctag.clone()),
+        };
+        let inner_start = if sigil == ' ' {
+            content_start
+        } else {
+            content_start + 1
+        };
+
+        let inner_len = source[inner_start..].find(&close).ok_or_else(|| {
+            format!(
+                "Unclosed tag at line {}",
+                source[..tag_start].matches('\n').count() + 1
+            )
+        })?;
+        let inner = source[inner_start..inner_start + inner_len].trim();
+        let tag_end = inner_start + inner_len + close.len();
+
+        // A standalone tag is alone on its line, and the whole line is removed from the output.
+        let line_start = source[..tag_start].rfind('\n').map_or(0, |i| i + 1);
+        let rest = &source[tag_end..];
+        let line_end = rest.find('\n');
+        let standalone = matches!(sigil, '#' | '^' | '/' | '!' | '>' | '=')
+            && source[line_start..tag_start].trim().is_empty()
+            && rest[..line_end.unwrap_or(rest.len())].trim().is_empty();
+
+        let (text_end, indent) = if standalone {
+            (line_start, &source[line_start..tag_start])
+        } else {
+            (tag_start, "")
+        };
+
+        if text_end > pos {
+            nodes.push(Node::Text(source[pos..text_end].to_owned()));
+        }
+
+        pos = match (standalone, line_end) {
+            (true, Some(line_end)) => tag_end + line_end + 1,
+            (true, None) => source.len(),
+            (false, _) => tag_end,
+        };
+
+        match sigil {
+            '#' | '^' => {
+                sections.push((inner.to_owned(), sigil == '^', mem::take(&mut nodes)));
+            }
+            '/' => {
+                let (name, inverted, parent) = sections
+                    .pop()
+                    .ok_or_else(|| format!("Closing unopened section `{inner}`"))?;
+
+                if name != inner {
+                    return Err(format!(
+                        "Section `{name}` is closed by a mismatching tag `{inner}`"
+                    ));
+                }
+
+                let children = mem::replace(&mut nodes, parent);
+                nodes.push(Node::Section {
+                    name,
+                    inverted,
+                    children,
+                });
+            }
+            '!' => {}
+            '>' => nodes.push(Node::Partial {
+                name: inner.to_owned(),
+                indent: indent.to_owned(),
+            }),
+            '=' => {
+                let mut delimiters = inner.split_whitespace();
+                match (delimiters.next(), delimiters.next(), delimiters.next()) {
+                    (Some(open), Some(close), None) => {
+                        otag = open.to_owned();
+                        ctag = close.to_owned();
+                    }
+                    _ => return Err(format!("Invalid delimiters `{inner}`")),
+                }
+            }
+            '{' | '&' => nodes.push(Node::Variable {
+                name: inner.to_owned(),
+                escape: false,
+            }),
+            _ => nodes.push(Node::Variable {
+                name: inner.to_owned(),
+                escape: true,
+            }),
+        }
+    }
+
+    if let Some((name, _, _)) = sections.last() {
+        return Err(format!("Unclosed section `{name}`"));
+    }
+
+    if pos < source.len() {
+        nodes.push(Node::Text(source[pos..].to_owned()));
+    }
+
+    Ok(nodes)
+}
+
+struct Mustache<'a> {
+    partials_dirs: &'a [PathBuf],
+    /// Fail on missing variables, rather than rendering them as empty. Sections are not affected.
+    strict: bool,
+    /// Partials must not resolve outside of this directory.
+    root: Option<&'a AbsolutePath>,
+    partials: &'a RefCell<Partials>,
+}
+
+impl Mustache<'_> {
+    fn render(
+        &self,
+        nodes: &[Node],
+        stack: &mut Vec<&Value>,
+        out: &mut String,
+        depth: usize,
+    ) -> Result<(), RenditError> {
+        for node in nodes {
+            match node {
+                Node::Text(text) => out.push_str(text),
+                Node::Variable { name, escape } => {
+                    let value = match lookup(stack, name) {
+                        Some(Value::String(s)) => s.to_owned(),
+                        None if self.strict => {
+                            return Err(RenditError::engine(
+                                "Mustache is unable to render the template.",
+                                format!("Variable `{name}` is not set"),
+                            ))
+                        }
+                        Some(Value::Null) | None => String::new(),
+                        Some(other) => other.to_string(),
+                    };
+                    if *escape {
+                        html_escape(&value, out);
+                    } else {
+                        out.push_str(&value);
+                    }
+                }
+                Node::Section {
+                    name,
+                    inverted,
+                    children,
+                } => {
+                    let value = lookup(stack, name);
+                    let falsy =
+                        matches!(value, None | Some(Value::Null) | Some(Value::Bool(false)))
+                            || matches!(value, Some(Value::Array(items)) if items.is_empty());
+
+                    if *inverted {
+                        if falsy {
+                            self.render(children, stack, out, depth)?;
+                        }
+                        continue;
+                    }
+
+                    match value {
+                        Some(Value::Array(items)) => {
+                            for item in items {
+                                stack.push(item);
+                                self.render(children, stack, out, depth)?;
+                                stack.pop();
+                            }
+                        }
+                        Some(value) if !falsy => {
+                            stack.push(value);
+                            self.render(children, stack, out, depth)?;
+                            stack.pop();
+                        }
+                        _ => {}
+                    }
+                }
+                Node::Partial { name, indent } => {
+                    if depth == MAX_PARTIAL_DEPTH {
+                        return Err(RenditError::engine(
+                            "Mustache is unable to render the template.",
+                            format!("Partial `{name}` is nested more than {MAX_PARTIAL_DEPTH} levels deep, it may include itself without end"),
+                        ));
+                    }
+
+                    let Some(nodes) = self.partial(name, indent)? else {
+                        continue;
+                    };
+
+                    self.render(&nodes, stack, out, depth + 1)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Parses the partial, indented as a whole if it is standalone, or returns the one that was already parsed.
+    fn partial(&self, name: &str, indent: &str) -> Result<Option<Rc<Vec<Node>>>, RenditError> {
+        let key = (name.to_owned(), indent.to_owned());
+
+        if let Some(nodes) = self.partials.borrow().get(&key) {
+            return Ok(nodes.clone());
+        }
+
+        let nodes = match self.load_partial(name)? {
+            Some(source) => {
+                let source = if indent.is_empty() {
+                    source
+                } else {
+                    source
+                        .split_inclusive('\n')
+                        .map(|line| format!("{indent}{line}"))
+                        .collect()
+                };
+
+                let nodes = parse(&source).map_err(|e| {
+                    RenditError::engine(
+                        format!("Mustache is unable to parse the partial `{name}`."),
+                        e,
+                    )
+                })?;
+                Some(Rc::new(nodes))
+            }
+            None => {
+                log::debug!("Mustache: Partial `{name}` was not found");
+                None
+            }
+        };
+
+        self.partials.borrow_mut().insert(key, nodes.clone());
+        Ok(nodes)
+    }
+
+    /// Loads `<name>.mustache` or `<name>` from the first partials directory that has it. A missing partial renders as empty.
+    fn load_partial(&self, name: &str) -> Result<Option<String>, RenditError> {
+        // Names are relative paths below the directories, such as `layouts/base`.
+        if !Path::new(name)
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+        {
+            return Ok(None);
+        }
+
+        let Some(path) = self.partials_dirs.iter().find_map(|dir| {
+            [dir.join(format!("{name}.mustache")), dir.join(name)]
+                .into_iter()
+                .find(|p| p.is_file())
+        }) else {
+            return Ok(None);
+        };
+
+        log::debug!(
+            "Mustache: Partial `{name}` is \"{}\"",
+            path.to_string_lossy()
+        );
+
+        if let Some(root) = self.root {
+            AbsolutePath::from(path.as_path()).restrict_to(root)?;
+        }
+
+        fs::read_to_string(&path)
+            .map(Some)
+            .map_err(|e| RenditError::TemplateFile(path, e))
+    }
+}
+
+/// Resolves a (dotted) name against the context stack, top to bottom.
+/// Only the first part of a dotted name is looked up through the stack.
+fn lookup<'v>(stack: &[&'v Value], name: &str) -> Option<&'v Value> {
+    if name == "." {
+        return stack.last().copied();
+    }
+
+    let mut parts = name.split('.');
+    let first = parts.next()?;
+
+    let mut value = stack
+        .iter()
+        .rev()
+        .find_map(|context| context.as_object()?.get(first))?;
+
+    for part in parts {
+        value = value.as_object()?.get(part)?;
+    }
+    Some(value)
+}
+
+fn html_escape(value: &str, out: &mut String) {
+    for c in value.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '"' => out.push_str("&quot;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            c => out.push(c),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renditlib::test_util::TempDir;
+    use serde_json::json;
+
+    fn compile(dir: &std::path::Path, source: &str) -> Box<dyn CompiledTemplate + 'static> {
+        MustacheRenderer
+            .compile(
+                &TemplateData {
+                    contents: Rc::new(source.to_owned()),
+                    file_path: Some(dir.join("t.mustache").into()),
+                },
+                &RenderOptions::default(),
+            )
+            .unwrap()
+    }
+
+    #[test]
+    fn partials_are_parsed_once() {
+        let dir = TempDir::new("mustache-cache");
+        fs::write(dir.join("item.mustache"), "<{{.}}>").unwrap();
+
+        let template = compile(&dir, "{{#items}}{{> item}}{{/items}}");
+        assert_eq!(
+            template.render(&json!({ "items": [1, 2] })).unwrap(),
+            "<1><2>"
+        );
+
+        fs::remove_file(dir.join("item.mustache")).unwrap();
+        assert_eq!(template.render(&json!({ "items": [3] })).unwrap(), "<3>");
+    }
+
+    #[test]
+    fn partials_recurse_with_the_data() {
+        let dir = TempDir::new("mustache-recursion");
+        fs::write(
+            dir.join("node.mustache"),
+            "{{name}}({{#children}}{{> node}}{{/children}})",
+        )
+        .unwrap();
+
+        let tree = json!({ "name": "a", "children": [{ "name": "b", "children": [{ "name": "c", "children": [] }] }] });
+        assert_eq!(
+            compile(&dir, "{{> node}}").render(&tree).unwrap(),
+            "a(b(c()))"
+        );
+    }
+
+    #[test]
+    fn partials_that_include_themselves_fail() {
+        let dir = TempDir::new("mustache-endless");
+        fs::write(dir.join("self.mustache"), "{{> self}}").unwrap();
+
+        let error = compile(&dir, "{{> self}}").render(&json!({})).unwrap_err();
+
+        assert!(matches!(error, RenditError::Engine { .. }));
+        let source = std::error::Error::source(&error).unwrap().to_string();
+        assert!(
+            source.contains("Partial `self` is nested more than 100 levels deep"),
+            "{source}"
+        );
+    }
+}
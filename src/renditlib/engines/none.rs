@@ -0,0 +1,28 @@
+use super::{EngineInfo, Renderer};
+use crate::renditlib::{error::RenditError, render::RenderOptions, template::TemplateData};
+
+/// Passes the template contents through as is.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NoneRenderer;
+
+impl Renderer for NoneRenderer {
+    fn info(&self) -> EngineInfo {
+        EngineInfo {
+            name: "none",
+            aliases: &[],
+            extensions: &[],
+            magic_keys: &[],
+            post_processor: false,
+            output_extension: None,
+        }
+    }
+
+    fn render(
+        &self,
+        template: &TemplateData,
+        _context: &serde_json::Value,
+        _options: &RenderOptions,
+    ) -> Result<String, RenditError> {
+        Ok(template.contents.to_string())
+    }
+}
@@ -0,0 +1,202 @@
+use regex::Regex;
+use serde_json::Value;
+
+use super::{EngineInfo, Renderer};
+use crate::renditlib::{error::RenditError, render::RenderOptions, template::TemplateData};
+
+/// Substitutes `${name}` and `${name:-default}` variables, in the manner of `envsubst`.
+///
+/// Names are dotted paths into the context (`${db.host}`, `${servers.0}`), and may optionally
+/// fall back to the process environment. `$${name}` is left as the literal `${name}`.
+/// Everything else in the template, such as `{{` or `{%`, is kept as is.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SubstRenderer {
+    env: bool,
+    strict: bool,
+}
+
+impl SubstRenderer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Look up variables that are missing from the context in the process environment.
+    pub fn env(mut self, env: bool) -> Self {
+        self.env = env;
+        self
+    }
+
+    /// Fail on variables that are not set and have no default, instead of substituting an empty value.
+    /// [`RenderOptions::strict`] takes precedence, when set.
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    fn lookup(&self, context: &Value, name: &str) -> Option<String> {
+        let value = name
+            .split('.')
+            .try_fold(context, |value, part| match value {
+                Value::Object(map) => map.get(part),
+                Value::Array(items) => items.get(part.parse::<usize>().ok()?),
+                _ => None,
+            });
+
+        match value {
+            Some(Value::Null) | None => {
+                if self.env {
+                    std::env::var(name).ok()
+                } else {
+                    None
+                }
+            }
+            Some(Value::String(s)) => Some(s.to_owned()),
+            Some(other) => Some(other.to_string()),
+        }
+    }
+}
+
+impl Renderer for SubstRenderer {
+    fn info(&self) -> EngineInfo {
+        super::SUBST
+    }
+
+    fn render(
+        &self,
+        template: &TemplateData,
+        context: &serde_json::Value,
+        options: &RenderOptions,
+    ) -> Result<String, RenditError> {
+        let re = Regex::new(r#"(?P<escape>\$)?\$\{(?P<name>[\w.\-]+)(?::-(?P<default>[^}]*))?\}"#)
+            .expect("Bad regex pattern.");
+
+        let strict = options.strict.unwrap_or(self.strict);
+
+        let contents = template.contents.as_str();
+        let mut rendered = String::with_capacity(contents.len());
+        let mut last = 0;
+
+        for cap in re.captures_iter(contents) {
+            let m = cap.get(0).expect("Capture without a match?");
+            rendered.push_str(&contents[last..m.start()]);
+            last = m.end();
+
+            if cap.name("escape").is_some() {
+                rendered.push_str(&m.as_str()[1..]);
+                continue;
+            }
+
+            let name = &cap["name"];
+
+            let default = cap.name("default").map(|d| d.as_str());
+
+            // Like the shell, `:-` applies the default for both unset and empty variables.
+            match (self.lookup(context, name), default) {
+                (Some(value), Some(default)) if value.is_empty() => rendered.push_str(default),
+                (Some(value), _) => rendered.push_str(&value),
+                (None, Some(default)) => rendered.push_str(default),
+                (None, None) if strict => {
+                    let line = contents[..m.start()].matches('\n').count() + 1;
+                    return Err(RenditError::engine(
+                        "Subst is unable to render the template.",
+                        format!("Variable `{name}` is not set (line {line})"),
+                    ));
+                }
+                (None, None) => {}
+            }
+        }
+        rendered.push_str(&contents[last..]);
+
+        Ok(rendered)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::rc::Rc;
+
+    fn render(
+        renderer: SubstRenderer,
+        source: &str,
+        strict: Option<bool>,
+    ) -> Result<String, RenditError> {
+        let template = TemplateData {
+            contents: Rc::new(source.to_owned()),
+            file_path: None,
+        };
+        let options = RenderOptions {
+            strict,
+            ..Default::default()
+        };
+        let context = json!({
+            "name": "World",
+            "empty": "",
+            "db": { "host": "localhost", "port": 5432 },
+            "servers": ["a", "b"],
+        });
+        renderer.render(&template, &context, &options)
+    }
+
+    #[test]
+    fn substitutes_dotted_paths() {
+        let rendered = render(
+            SubstRenderer::new(),
+            "${name} ${db.host}:${db.port} ${servers.1} {{ name }}",
+            None,
+        );
+        assert_eq!(rendered.unwrap(), "World localhost:5432 b {{ name }}");
+    }
+
+    #[test]
+    fn keeps_escaped_variables() {
+        let rendered = render(SubstRenderer::new(), "$${name} $${missing:-x} $$", None);
+        assert_eq!(rendered.unwrap(), "${name} ${missing:-x} $$");
+    }
+
+    #[test]
+    fn applies_defaults_to_unset_and_empty_variables() {
+        let rendered = render(
+            SubstRenderer::new().strict(true),
+            "${missing:-a} ${empty:-b} ${name:-c} ${db.missing:-} ${servers.9:-d}",
+            None,
+        );
+        assert_eq!(rendered.unwrap(), "a b World  d");
+    }
+
+    #[test]
+    fn strict_fails_on_missing_variables() {
+        let source = "${name}\n${missing}";
+
+        assert_eq!(
+            render(SubstRenderer::new(), source, None).unwrap(),
+            "World\n"
+        );
+
+        let error = render(SubstRenderer::new().strict(true), source, None).unwrap_err();
+        assert_eq!(
+            std::error::Error::source(&error).unwrap().to_string(),
+            "Variable `missing` is not set (line 2)"
+        );
+
+        // The render options take precedence over the renderer.
+        assert!(render(SubstRenderer::new(), source, Some(true)).is_err());
+        assert!(render(SubstRenderer::new().strict(true), source, Some(false)).is_ok());
+    }
+
+    #[test]
+    fn falls_back_to_the_environment() {
+        std::env::set_var("RENDIT_SUBST_TEST", "env");
+        let source = "${RENDIT_SUBST_TEST} ${name} ${RENDIT_SUBST_UNSET:-x}";
+
+        assert_eq!(
+            render(SubstRenderer::new(), source, None).unwrap(),
+            " World x"
+        );
+        assert_eq!(
+            render(SubstRenderer::new().env(true), source, None).unwrap(),
+            "env World x"
+        );
+    }
+}
@@ -0,0 +1,218 @@
+use regex::Regex;
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+    error::Error,
+    fs,
+    path::PathBuf,
+};
+use tera::Tera;
+
+use super::{CompiledTemplate, EngineInfo, Renderer};
+use crate::renditlib::{
+    error::RenditError,
+    render::RenderOptions,
+    template::{TemplateData, TemplateExtension},
+};
+
+/// Renders templates with [Tera](https://tera.netlify.app/docs/#templates).
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TeraRenderer;
+
+impl Renderer for TeraRenderer {
+    fn info(&self) -> EngineInfo {
+        super::TERA
+    }
+
+    fn render(
+        &self,
+        template: &TemplateData,
+        context: &serde_json::Value,
+        options: &RenderOptions,
+    ) -> Result<String, RenditError> {
+        self.compile(template, options)?.render(context)
+    }
+
+    fn compile<'a>(
+        &'a self,
+        template: &TemplateData,
+        options: &RenderOptions,
+    ) -> Result<Box<dyn CompiledTemplate + 'a>, RenditError> {
+        if options.strict == Some(false) {
+            return Err(RenditError::LenientNotSupported(super::TERA.name));
+        }
+
+        // A template without a file, such as one from STDIN or a library's source string,
+        // only sees the include directories.
+        let templates_dirs = match &template.file_path {
+            Some(template_file) => options.template_dirs(
+                template_file
+                    .parent()
+                    .ok_or(RenditError::EmptyPath)?
+                    .to_path_buf(),
+            ),
+            None => options
+                .include_dirs
+                .iter()
+                .map(|dir| dir.to_path_buf())
+                .collect(),
+        };
+
+        // TODO: Better to create an instance of `Tera::default()` and have a deep scan for the templates to add only the references ones into a HashSet, than to add every file in the template's directory.
+        let mut tera = Tera::default();
+        let mut outside_root = OutsideRoot::new();
+
+        // Templates of a directory don't replace the ones of the same name from an earlier directory.
+        // The inheritance chains are built along with the main template, once all of the directories are loaded,
+        // so templates may extend the ones from any directory.
+        // The glob follows symlinks, which may lead out of the root directory. Such templates are left out,
+        // and only fail the render once a template uses them.
+        for dir in templates_dirs {
+            let templates_dir_glob = dir.join("**").join("*.*");
+            let templates_dir_glob = templates_dir_glob.to_string_lossy();
+
+            log::debug!("Tera templates path: {templates_dir_glob}");
+
+            let templates = Tera::parse(&templates_dir_glob)
+                .map_err(|e| RenditError::engine("Unable to create Tera instance", e))?;
+
+            for (name, template) in templates.templates {
+                if tera.templates.contains_key(&name) || outside_root.contains_key(&name) {
+                    continue;
+                }
+
+                match template.path.as_deref().map(|path| options.restrict(path)) {
+                    Some(Err(RenditError::OutsideRoot { path, root })) => {
+                        log::debug!(
+                            "Tera: Leaving out \"{}\", outside of the root directory",
+                            path.display()
+                        );
+                        outside_root.insert(name, (path, root));
+                    }
+                    Some(Err(e)) => return Err(e),
+                    _ => {
+                        tera.templates.insert(name, template);
+                    }
+                }
+            }
+        }
+
+        // Force extension or auto detect (default `.html`)
+        let template_type = if let TemplateExtension::Force(ext) = &options.extension {
+            log::debug!("Tera: Forcing extension \"{ext}\"");
+            Cow::Borrowed(ext.as_str())
+        } else if let Some(path) = &template.file_path {
+            match path.extension() {
+                Some(ext) => ext.to_string_lossy(),
+                None => Cow::Borrowed("html"),
+            }
+        } else {
+            Cow::Borrowed("html")
+        };
+
+        log::debug!("Tera: Using extension \"{template_type}\"");
+        let in_memory_template = format!("__in_memory__.{template_type}");
+
+        // Adds a virtual in-memory file for the main template. We need the `.html` extension to enforce HTML escaping.
+        tera.add_raw_template(&in_memory_template, &template.contents)
+            .map_err(|e| {
+                outside_root_error(&outside_root, &e).unwrap_or_else(|| {
+                    RenditError::engine(
+                        "Tera is unable to add the main template as raw template.",
+                        e,
+                    )
+                })
+            })?;
+
+        log_references(&tera, &template.contents);
+
+        Ok(Box::new(CompiledTera {
+            tera,
+            template_name: in_memory_template,
+            outside_root,
+        }))
+    }
+}
+
+/// A Tera instance with the main template and the templates of its directory loaded.
+struct CompiledTera {
+    tera: Tera,
+    template_name: String,
+    outside_root: OutsideRoot,
+}
+
+/// The templates left out for being outside of the root directory, by name, with their path and the root.
+type OutsideRoot = HashMap<String, (PathBuf, PathBuf)>;
+
+/// Reports [`RenditError::OutsideRoot`] when Tera fails for a template that was left out.
+/// Its messages name templates in quotes or backticks, and the candidates of an `include` as `'[a, b]'`.
+fn outside_root_error(outside_root: &OutsideRoot, error: &tera::Error) -> Option<RenditError> {
+    let mut names = HashSet::new();
+    let mut source: Option<&dyn Error> = Some(error);
+    while let Some(e) = source {
+        let message = e.to_string();
+        names.extend(
+            message
+                .split(['\'', '`'])
+                .skip(1)
+                .step_by(2)
+                .flat_map(|quoted| quoted.trim_matches(['[', ']']).split(", "))
+                .map(str::to_owned),
+        );
+        source = e.source();
+    }
+
+    outside_root.iter().find_map(|(name, (path, root))| {
+        names.contains(name).then(|| RenditError::OutsideRoot {
+            path: path.clone(),
+            root: root.clone(),
+        })
+    })
+}
+
+impl CompiledTemplate for CompiledTera {
+    fn render(&self, context: &serde_json::Value) -> Result<String, RenditError> {
+        let context = tera::Context::from_value(context.clone())
+            .map_err(|e| RenditError::engine("Tera rejected Context object.", e))?;
+
+        self.tera
+            .render(&self.template_name, &context)
+            .map_err(|e| {
+                outside_root_error(&self.outside_root, &e).unwrap_or_else(|| {
+                    RenditError::engine("Tera is unable to render the template.", e)
+                })
+            })
+    }
+}
+
+/// Logs the file that each `include`, `import` and `extends` reference resolves to,
+/// following the references of the referenced templates.
+fn log_references(tera: &Tera, contents: &str) {
+    if !log::log_enabled!(log::Level::Debug) {
+        return;
+    }
+
+    let re = Regex::new(r#"\{%-?\s*(?:include|import|extends)\s+["'](?P<name>[^"']+)["']"#)
+        .expect("Bad regex pattern.");
+
+    let mut visited = HashSet::new();
+    let mut queue = vec![contents.to_owned()];
+
+    while let Some(source) = queue.pop() {
+        for cap in re.captures_iter(&source) {
+            let name = &cap["name"];
+
+            if !visited.insert(name.to_owned()) {
+                continue;
+            }
+
+            match tera.templates.get(name).and_then(|t| t.path.as_ref()) {
+                Some(path) => {
+                    log::debug!("Tera: `{name}` is \"{path}\"");
+                    queue.extend(fs::read_to_string(path));
+                }
+                None => log::debug!("Tera: `{name}` is not found"),
+            }
+        }
+    }
+}
@@ -0,0 +1,90 @@
+use std::path::PathBuf;
+
+/// Boxed source error of an underlying template engine.
+pub type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+#[non_exhaustive]
+#[derive(thiserror::Error, Debug)]
+pub enum RenditError {
+    #[error("Path must not be empty or root")]
+    EmptyPath,
+
+    #[error("Provided unsupported engine `{0}`")]
+    UnknownEngine(String),
+
+    #[error("Engine `{engine}` is not built in. Rebuild with the `{feature}` cargo feature to enable it")]
+    EngineNotBuiltIn {
+        engine: String,
+        feature: &'static str,
+    },
+
+    #[error("Engine `{0}` always fails on missing variables, it is unable to render them as empty (`--no-strict` or `strict=false`)")]
+    LenientNotSupported(&'static str),
+
+    #[error("Unable to guess the engine of the template, it looks like either {}. Please choose one with `--engine`", .0.join(" or "))]
+    AmbiguousEngine(Vec<String>),
+
+    #[error("Either a template source or a template path must be provided")]
+    MissingTemplate,
+
+    #[error("Failed to get current working directory")]
+    CurrentDir(#[source] std::io::Error),
+
+    #[error("Unable to load template file \"{}\"", .0.display())]
+    TemplateFile(PathBuf, #[source] std::io::Error),
+
+    #[error("Unable to load context file \"{}\"", .0.display())]
+    ContextFile(PathBuf, #[source] std::io::Error),
+
+    #[error("Unable to parse {format} context from file \"{}\"{}", .path.display(), at_location(.location))]
+    ContextParse {
+        path: PathBuf,
+        format: &'static str,
+        /// The (line, column) of the error, starting at 1.
+        location: Option<(usize, usize)>,
+        #[source]
+        source: BoxError,
+    },
+
+    #[error("Unable to parse the {format} front matter of the template{}", at_location(.location))]
+    FrontMatter {
+        format: &'static str,
+        /// The (line, column) of the error in the template, starting at 1.
+        location: Option<(usize, usize)>,
+        #[source]
+        source: BoxError,
+    },
+
+    #[error("\"{}\" is outside of the root directory \"{}\"", .path.display(), .root.display())]
+    OutsideRoot { path: PathBuf, root: PathBuf },
+
+    #[error("Environment variable `{0}` is not valid UTF-8")]
+    EnvVar(String),
+
+    #[error("Unable to set `{path}` in the context: {reason}")]
+    ContextSet { path: String, reason: String },
+
+    #[error("{message}")]
+    Engine {
+        message: String,
+        #[source]
+        source: BoxError,
+    },
+}
+
+fn at_location(location: &Option<(usize, usize)>) -> String {
+    match location {
+        Some((line, column)) => format!(" at line {line}, column {column}"),
+        None => String::new(),
+    }
+}
+
+impl RenditError {
+    /// Wraps an error returned by an underlying template engine.
+    pub fn engine<M: Into<String>, E: Into<BoxError>>(message: M, source: E) -> Self {
+        RenditError::Engine {
+            message: message.into(),
+            source: source.into(),
+        }
+    }
+}
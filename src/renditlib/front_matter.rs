@@ -0,0 +1,373 @@
+use serde_json::{Map, Value};
+use std::mem;
+
+use super::{
+    context::ContextFormat,
+    error::{BoxError, RenditError},
+    registry::{find_magic_comment, EngineRegistry, MagicComment},
+};
+
+/// Settings and context defaults of a template, given by a YAML (`---`) or TOML (`+++`) block at its top.
+/// The block may come after the magic comment.
+///
+/// [`prepare`](super::prepare) only splits it off templates whose engine is forced, or selected by their extension
+/// or magic comment, leaving the `---` block of a plain Markdown file, for instance, in place.
+///
+/// The `engine`, `extension`, `output`, `context` and `strict` keys configure the rendering, the rest are context defaults.
+///
+/// The options of the magic comment (`<!--template tera ext=html output=../dist/index.html strict-->`)
+/// fill in for the settings that the front matter doesn't give.
+///
+/// ```text
+/// <!--template tera-->
+/// ---
+/// title: Welcome
+/// output: ../dist/index.html
+/// ---
+/// <h1>{{ title }}</h1>
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FrontMatter {
+    /// Selects the engine, instead of the file extension or the magic comment.
+    pub engine: Option<String>,
+    /// Treats the template as a file of the given extension.
+    pub extension: Option<String>,
+    /// The output file, relative to the template's directory.
+    pub output: Option<String>,
+    /// Context files, relative to the template's directory, loaded instead of the default ones.
+    pub context: Vec<String>,
+    /// Fails on variables that are missing from the context, or renders them as empty.
+    pub strict: Option<bool>,
+    /// The rest of the values, which the context is merged over.
+    pub defaults: Value,
+}
+
+impl FrontMatter {
+    /// Splits the front matter off `contents`, keeping a magic comment that comes before it.
+    ///
+    /// Returns `None` if there is neither a front matter nor magic comment options.
+    /// The magic comment is looked up for the engines of `registry`.
+    pub fn split(
+        contents: &str,
+        registry: &EngineRegistry,
+    ) -> Result<Option<(FrontMatter, String)>, RenditError> {
+        let magic_comment = find_magic_comment(contents, |key| registry.is_magic_key(key));
+
+        let (front_matter, contents) = match Self::split_block(contents, &magic_comment)? {
+            Some((front_matter, contents)) => (Some(front_matter), contents),
+            None => (None, contents.to_owned()),
+        };
+
+        match magic_comment.filter(|magic_comment| !magic_comment.options.is_empty()) {
+            Some(magic_comment) => {
+                let mut front_matter = front_matter.unwrap_or_default();
+                front_matter.fill_in(magic_comment.options);
+                Ok(Some((front_matter, contents)))
+            }
+            None => Ok(front_matter.map(|front_matter| (front_matter, contents))),
+        }
+    }
+
+    fn split_block(
+        contents: &str,
+        magic_comment: &Option<MagicComment>,
+    ) -> Result<Option<(FrontMatter, String)>, RenditError> {
+        let start = magic_comment
+            .as_ref()
+            .filter(|magic_comment| magic_comment.line.start == 0)
+            .map_or(0, |magic_comment| magic_comment.line.end);
+
+        let mut lines = contents[start..].split_inclusive('\n');
+
+        let (fence, format) = match lines.next().map(str::trim_end) {
+            Some("---") => ("---", ContextFormat::Yaml),
+            Some("+++") => ("+++", ContextFormat::Toml),
+            _ => return Ok(None),
+        };
+
+        let body_start = contents[start..]
+            .find('\n')
+            .map_or(contents.len(), |i| start + i + 1);
+        let mut offset = body_start;
+
+        for line in lines {
+            if line.trim_end() == fence {
+                // Lines before the body, to point errors at the template's own lines.
+                let first_line = contents[..body_start].matches('\n').count();
+                let front_matter = Self::parse(&contents[body_start..offset], format, first_line)?;
+                log::info!(
+                    "Front matter: {} lines of {} left out of the output",
+                    contents[body_start..offset].lines().count(),
+                    format.name()
+                );
+
+                let stripped =
+                    format!("{}{}", &contents[..start], &contents[offset + line.len()..]);
+                return Ok(Some((front_matter, stripped)));
+            }
+            offset += line.len();
+        }
+
+        // Without a closing fence, it is just the template's own text.
+        Ok(None)
+    }
+
+    fn parse(source: &str, format: ContextFormat, first_line: usize) -> Result<Self, RenditError> {
+        let error = |source: BoxError, location: Option<(usize, usize)>| RenditError::FrontMatter {
+            format: format.name(),
+            location: location.map(|(line, column)| (line + first_line, column)),
+            source,
+        };
+
+        let value = if source.trim().is_empty() {
+            Value::Null
+        } else {
+            format
+                .parse(source)
+                .map_err(|(source, location)| error(source, location))?
+        };
+
+        let mut map = match value {
+            Value::Object(map) => map,
+            Value::Null => Map::new(),
+            _ => return Err(error("Expected keys and values".into(), None)),
+        };
+
+        let string = |map: &mut Map<String, Value>, key: &str| match map.remove(key) {
+            None | Some(Value::Null) => Ok(None),
+            Some(Value::String(s)) => Ok(Some(s)),
+            Some(_) => Err(error(format!("`{key}` must be a string").into(), None)),
+        };
+
+        let engine = string(&mut map, "engine")?;
+        let extension = string(&mut map, "extension")?;
+        let output = string(&mut map, "output")?;
+
+        let strict = match map.remove("strict") {
+            None | Some(Value::Null) => None,
+            Some(Value::Bool(strict)) => Some(strict),
+            Some(_) => return Err(error("`strict` must be a boolean".into(), None)),
+        };
+
+        let context = match map.remove("context") {
+            None | Some(Value::Null) => Vec::new(),
+            Some(Value::String(s)) => vec![s],
+            Some(Value::Array(items)) => items
+                .into_iter()
+                .map(|item| match item {
+                    Value::String(s) => Ok(s),
+                    _ => Err(error("`context` must be a list of file paths".into(), None)),
+                })
+                .collect::<Result<_, _>>()?,
+            Some(_) => {
+                return Err(error(
+                    "`context` must be a file path or a list of them".into(),
+                    None,
+                ))
+            }
+        };
+
+        Ok(FrontMatter {
+            engine,
+            extension,
+            output,
+            context,
+            strict,
+            defaults: Value::Object(map),
+        })
+    }
+
+    /// Sets the magic comment options (`ext=html context=shared.json strict`) that aren't already set.
+    fn fill_in(&mut self, options: &str) {
+        let mut context = Vec::new();
+
+        for option in option_words(options) {
+            let (key, value) = match option.split_once('=') {
+                Some((key, value)) => (key, Some(value)),
+                None => (option.as_str(), None),
+            };
+
+            match (key.to_lowercase().as_str(), value) {
+                ("ext" | "extension", Some(extension)) => {
+                    self.extension.get_or_insert_with(|| extension.to_owned());
+                }
+                ("output", Some(output)) => {
+                    self.output.get_or_insert_with(|| output.to_owned());
+                }
+                ("context", Some(context_file)) => context.push(context_file.to_owned()),
+                ("strict", None) => {
+                    self.strict.get_or_insert(true);
+                }
+                ("strict", Some(strict @ ("true" | "false"))) => {
+                    self.strict.get_or_insert(strict == "true");
+                }
+                _ => log::warn!("Ignoring the magic comment option `{option}`"),
+            }
+        }
+
+        if self.context.is_empty() {
+            self.context = context;
+        }
+    }
+}
+
+/// Splits on whitespace, except within double quotes (`output="my page.html"`).
+fn option_words(options: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut quoted = false;
+
+    for c in options.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            c if c.is_whitespace() && !quoted => {
+                if !word.is_empty() {
+                    words.push(mem::take(&mut word));
+                }
+            }
+            c => word.push(c),
+        }
+    }
+
+    if !word.is_empty() {
+        words.push(word);
+    }
+    words
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn split(contents: &str) -> (FrontMatter, String) {
+        FrontMatter::split(contents, &EngineRegistry::default())
+            .unwrap()
+            .expect("front matter")
+    }
+
+    #[test]
+    fn yaml_fence() {
+        let (front_matter, contents) =
+            split("---\ntitle: Welcome\noutput: index.html\n---\n<h1>{{ title }}</h1>\n");
+
+        assert_eq!(front_matter.output.as_deref(), Some("index.html"));
+        assert_eq!(front_matter.defaults, json!({ "title": "Welcome" }));
+        assert_eq!(contents, "<h1>{{ title }}</h1>\n");
+    }
+
+    #[test]
+    fn toml_fence() {
+        let (front_matter, contents) =
+            split("+++\nstrict = true\ncontext = \"site.json\"\n+++\nbody");
+
+        assert_eq!(front_matter.strict, Some(true));
+        assert_eq!(front_matter.context, ["site.json"]);
+        assert_eq!(contents, "body");
+    }
+
+    #[test]
+    fn fence_after_magic_comment() {
+        let (front_matter, contents) =
+            split("<!--template tera-->\r\n---\r\nengine: minijinja\r\n---\r\nbody\r\n");
+
+        assert_eq!(front_matter.engine.as_deref(), Some("minijinja"));
+        assert_eq!(contents, "<!--template tera-->\r\nbody\r\n");
+    }
+
+    #[test]
+    fn empty_front_matter() {
+        let (front_matter, contents) = split("---\n---\nbody");
+
+        assert_eq!(
+            front_matter,
+            FrontMatter {
+                defaults: json!({}),
+                ..Default::default()
+            }
+        );
+        assert_eq!(contents, "body");
+    }
+
+    #[test]
+    fn no_front_matter() {
+        assert!(FrontMatter::split(
+            "body\n---\nnot: front matter\n---\n",
+            &EngineRegistry::default()
+        )
+        .unwrap()
+        .is_none());
+        assert!(
+            FrontMatter::split("-----\nrule\n-----\n", &EngineRegistry::default())
+                .unwrap()
+                .is_none()
+        );
+        // Without a closing fence, it is the template's own text.
+        assert!(
+            FrontMatter::split("---\ntitle: Welcome\nbody", &EngineRegistry::default())
+                .unwrap()
+                .is_none()
+        );
+        assert!(
+            FrontMatter::split("---\ntitle: Welcome\n+++\n", &EngineRegistry::default())
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn magic_comment_options() {
+        let (front_matter, contents) = split(
+            "<!--template tera ext=html output=\"my page.html\" context=a.json context=b.yaml strict-->\nbody",
+        );
+
+        assert_eq!(front_matter.extension.as_deref(), Some("html"));
+        assert_eq!(front_matter.output.as_deref(), Some("my page.html"));
+        assert_eq!(front_matter.context, ["a.json", "b.yaml"]);
+        assert_eq!(front_matter.strict, Some(true));
+        // The magic comment is left for the engine detection.
+        assert_eq!(contents, "<!--template tera ext=html output=\"my page.html\" context=a.json context=b.yaml strict-->\nbody");
+
+        let (front_matter, _) = split("<!--template tera strict=false unknown-->\n");
+        assert_eq!(front_matter.strict, Some(false));
+        assert!(
+            FrontMatter::split("<!--template tera-->\nbody", &EngineRegistry::default())
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn front_matter_over_magic_comment_options() {
+        let (front_matter, _) = split(
+            "<!--template tera ext=txt output=a.txt context=a.json strict-->\n---\nextension: html\noutput: b.html\ncontext: [b.json]\nstrict: false\n---\n",
+        );
+
+        assert_eq!(front_matter.extension.as_deref(), Some("html"));
+        assert_eq!(front_matter.output.as_deref(), Some("b.html"));
+        assert_eq!(front_matter.context, ["b.json"]);
+        assert_eq!(front_matter.strict, Some(false));
+    }
+
+    #[test]
+    fn errors_point_at_template_lines() {
+        // The block is cut short at its closing fence, on the fourth line of the template.
+        let error = FrontMatter::split(
+            "<!--template tera-->\n---\ntitle: [\n---\n",
+            &EngineRegistry::default(),
+        )
+        .unwrap_err();
+
+        assert!(
+            matches!(error, RenditError::FrontMatter { format: "YAML", location: Some((line, _)), .. } if line == 4)
+        );
+        assert!(matches!(
+            FrontMatter::split("---\n- a list\n---\n", &EngineRegistry::default()),
+            Err(RenditError::FrontMatter { .. })
+        ));
+        assert!(matches!(
+            FrontMatter::split("---\noutput: 1\n---\n", &EngineRegistry::default()),
+            Err(RenditError::FrontMatter { .. })
+        ));
+    }
+}
@@ -0,0 +1,224 @@
+use regex::Regex;
+
+use super::{registry::EngineRegistry, template::EngineName};
+
+/// Below this confidence, a guessed engine is reported as a warning.
+pub const LOW_CONFIDENCE: f32 = 0.6;
+
+/// Syntax that is specific to an engine: (engine name, pattern, weight of each match).
+///
+/// Jinja-like syntax that both Tera and MiniJinja support counts for Tera, while syntax that only
+/// MiniJinja supports counts for MiniJinja.
+const SYNTAX_RULES: &[(&str, &str, usize)] = &[
+    (
+        "tera",
+        r"\{%-?\s*(?:block|extends|macro|import|set|filter|raw)\b",
+        2,
+    ),
+    (
+        "tera",
+        r"\{%-?\s*(?:for\s+\w+(?:\s*,\s*\w+)?\s+in|if|elif)\b",
+        1,
+    ),
+    ("tera", r"\{\{-?[^}]*\|\s*\w+\s*-?\}\}", 1),
+    ("tera", r"\{\{-?[^}]*\|\s*\w+\(\s*\w+\s*=", 2),
+    ("tera", r"\bloop\.(?:index|first|last)\b", 1),
+    ("minijinja", r"\{%-?\s*(?:call|with|do|autoescape)\b", 3),
+    ("minijinja", r#"\{\{-?[^}]*\|\s*\w+\(\s*['"\d]"#, 2),
+    (
+        "liquid",
+        r"\{%-?\s*(?:assign|capture|unless|case|when|increment|decrement|render|liquid|comment|tablerow|cycle|elsif)\b",
+        3,
+    ),
+    ("liquid", r"\{\{-?[^}]*\|\s*\w+\s*:", 2),
+    ("liquid", r"\bforloop\.(?:index|first|last)\b", 2),
+    (
+        "handlebars",
+        r"\{\{~?#\*?(?:each|if|unless|with|let|inline)\b",
+        3,
+    ),
+    (
+        "handlebars",
+        r"\{\{~?(?:else|@index|@key|@first|@last|this)\b",
+        2,
+    ),
+    ("handlebars", r"\{\{~?>\s*[\w/]", 1),
+    ("mustache", r"\{\{[#^]\s*\w[\w.]*\s*\}\}", 2),
+    ("mustache", r"\{\{/\s*\w[\w.]*\s*\}\}", 1),
+    ("mustache", r"\{\{=\S+ \S+=\}\}", 3),
+    ("subst", r"\$\{\w+(?::-[^}]*)?\}", 2),
+    ("mjml", r"<mjml\b", 3),
+];
+
+/// The engine guessed out of the syntax of a template, by [`EngineRegistry::guess`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct EngineGuess {
+    /// The engine name, followed by a post processor (`tera+mjml`) if its syntax was found as well.
+    pub engine: EngineName,
+    /// The share of the engine-specific syntax that points at the engine, from 0 to 1.
+    pub confidence: f32,
+    /// The other engines whose syntax was found, best first, along with their share.
+    pub alternatives: Vec<(&'static str, f32)>,
+}
+
+impl EngineGuess {
+    /// Returns `true` if another engine is as likely as the guessed one.
+    pub fn is_ambiguous(&self) -> bool {
+        self.alternatives
+            .first()
+            .is_some_and(|(_, confidence)| *confidence >= self.confidence)
+    }
+}
+
+impl EngineRegistry {
+    /// Guesses the engine of a template by looking for syntax that is specific to one of the registered engines.
+    ///
+    /// Returns `None` if no such syntax is found. Only built-in engines are known.
+    pub fn guess(&self, contents: &str) -> Option<EngineGuess> {
+        let mut scores: Vec<(&'static str, usize)> = Vec::new();
+        let mut post_processors: Vec<&'static str> = Vec::new();
+
+        for (engine, pattern, weight) in SYNTAX_RULES {
+            let Some(info) = self.get(engine).map(|renderer| renderer.info()) else {
+                continue;
+            };
+
+            let matches = Regex::new(pattern)
+                .expect("Bad regex pattern.")
+                .find_iter(contents)
+                .count();
+
+            if matches == 0 {
+                continue;
+            }
+
+            log::trace!("Guess: {matches} matches of `{pattern}` for `{engine}`");
+
+            if info.post_processor {
+                if !post_processors.contains(&info.name) {
+                    post_processors.push(info.name);
+                }
+                continue;
+            }
+
+            match scores.iter_mut().find(|(name, _)| *name == info.name) {
+                Some((_, score)) => *score += matches * weight,
+                None => scores.push((info.name, matches * weight)),
+            }
+        }
+
+        // A stable sort keeps the registration order between equal scores.
+        scores.sort_by(|(_, a), (_, b)| b.cmp(a));
+
+        let total: usize = scores.iter().map(|(_, score)| score).sum();
+        let share = |score: usize| score as f32 / total as f32;
+
+        let mut scores = scores.into_iter();
+
+        let (engine, confidence) = match scores.next() {
+            Some((engine, score)) => (engine.to_owned(), share(score)),
+            // A template of a post processor alone, such as plain MJML.
+            None => (post_processors.first()?.to_string(), 1.0),
+        };
+
+        let engine = std::iter::once(engine.as_str())
+            .chain(post_processors.into_iter().filter(|p| *p != engine))
+            .collect::<Vec<_>>()
+            .join("+");
+
+        Some(EngineGuess {
+            engine,
+            confidence,
+            alternatives: scores
+                .map(|(engine, score)| (engine, share(score)))
+                .collect(),
+        })
+    }
+}
+
+#[cfg(all(
+    test,
+    feature = "engine-tera",
+    feature = "engine-liquid",
+    feature = "engine-handlebars",
+    feature = "engine-mjml"
+))]
+mod tests {
+    use super::*;
+    use crate::renditlib::{DetectionMethod, RenderBuilder, RenditError, TeraRenderer};
+
+    fn guess(contents: &str) -> Option<EngineGuess> {
+        EngineRegistry::default().guess(contents)
+    }
+
+    #[test]
+    fn guesses_by_specific_syntax() {
+        let tera =
+            guess("{% block body %}{% for x in xs %}{{ x }}{% endfor %}{% endblock %}").unwrap();
+        assert_eq!(tera.engine, "tera");
+        assert_eq!(tera.confidence, 1.0);
+        assert!(tera.alternatives.is_empty());
+
+        let handlebars = guess("{{#each items}}{{this}}{{/each}}").unwrap();
+        assert_eq!(handlebars.engine, "handlebars");
+        assert_eq!(handlebars.alternatives, [("mustache", 1.0 / 6.0)]);
+        assert!(!handlebars.is_ambiguous());
+
+        assert_eq!(guess("{% assign x = 1 %}{{ x }}").unwrap().engine, "liquid");
+        assert!(guess("Hello {{ name }}!").is_none());
+    }
+
+    #[test]
+    fn guesses_post_processors() {
+        assert_eq!(
+            guess("<mjml><mj-body></mj-body></mjml>").unwrap().engine,
+            "mjml"
+        );
+
+        let chain = guess("<mjml>{% block body %}{% endblock %}</mjml>").unwrap();
+        assert_eq!(chain.engine, "tera+mjml");
+        assert_eq!(chain.confidence, 1.0);
+    }
+
+    #[test]
+    fn equal_scores_are_ambiguous() {
+        let guess = guess("{% raw %}{% endraw %}{{ price | round: 2 }}").unwrap();
+
+        // Registration order decides between equal scores.
+        assert_eq!(guess.engine, "tera");
+        assert_eq!(guess.confidence, 0.5);
+        assert_eq!(guess.alternatives, [("liquid", 0.5)]);
+        assert!(guess.is_ambiguous());
+    }
+
+    #[test]
+    fn guesses_registered_engines_only() {
+        let mut registry = EngineRegistry::new();
+        registry.register(TeraRenderer);
+
+        assert!(registry.guess("{% assign x = 1 %}").is_none());
+        assert_eq!(
+            registry
+                .guess("{% raw %}{{ x | round: 2 }}")
+                .unwrap()
+                .engine,
+            "tera"
+        );
+    }
+
+    #[test]
+    fn auto_guess_fails_when_ambiguous() {
+        let render = |source: &str| {
+            RenderBuilder::new()
+                .template_source(source)
+                .engine(DetectionMethod::AUTO_GUESS)
+                .render()
+        };
+
+        assert_eq!(render("{% set x = 1 %}{{ x }}").unwrap().as_str(), "1");
+        assert!(matches!(
+            render("{% raw %}{% endraw %}{{ price | round: 2 }}"),
+            Err(RenditError::AmbiguousEngine(engines)) if engines == ["tera", "liquid"]
+        ));
+    }
+}
@@ -0,0 +1,63 @@
+//! Template engine detection and rendering, as used by the `rendit` CLI.
+//!
+//! The engine of a template is decided by its file extension (`.tera`, `.hbs` or `.liq`),
+//! or by a magic comment such as `<!--template tera-->`, unless it is forced explicitly.
+//! Both are looked up in an [`EngineRegistry`], which third-party engines can be added to.
+//!
+//! ```
+//! use rendit::renditlib::RenderBuilder;
+//!
+//! # #[cfg(feature = "engine-tera")]
+//! # {
+//! let rendered = RenderBuilder::new()
+//!     .template_source("Hello {{ name }}!")
+//!     .context(serde_json::json!({ "name": "World" }))
+//!     .engine("tera")
+//!     .render()?;
+//!
+//! assert_eq!(rendered.as_str(), "Hello World!");
+//! # }
+//! # Ok::<(), rendit::renditlib::RenditError>(())
+//! ```
+
+mod context;
+mod engines;
+mod error;
+mod front_matter;
+mod guess;
+mod path;
+mod registry;
+mod render;
+mod template;
+#[cfg(test)]
+mod test_util;
+
+pub use context::{
+    env_object, get_path, merge_values, read_env_file, read_env_vars, set_path, ArrayMerge,
+    ContextData, ContextFormat, CONTEXT_EXTENSIONS,
+};
+#[cfg(feature = "engine-handlebars")]
+pub use engines::HandlebarsRenderer;
+#[cfg(feature = "engine-liquid")]
+pub use engines::LiquidRenderer;
+#[cfg(feature = "engine-minijinja")]
+pub use engines::MiniJinjaRenderer;
+#[cfg(feature = "engine-mjml")]
+pub use engines::MjmlRenderer;
+#[cfg(feature = "engine-mustache")]
+pub use engines::MustacheRenderer;
+#[cfg(feature = "engine-subst")]
+pub use engines::SubstRenderer;
+#[cfg(feature = "engine-tera")]
+pub use engines::TeraRenderer;
+pub use engines::{CompiledTemplate, EngineInfo, NoneRenderer, Renderer};
+pub use error::{BoxError, RenditError};
+pub use front_matter::FrontMatter;
+pub use guess::EngineGuess;
+pub use path::AbsolutePath;
+pub use registry::EngineRegistry;
+pub use render::{prepare, render, LineEndings, PreparedTemplate, RenderBuilder, RenderOptions};
+pub use template::{
+    Contents, DetectionMethod, EngineName, RenderedTemplate, Template, TemplateData,
+    TemplateExtension,
+};
@@ -0,0 +1,353 @@
+use path_slash::PathExt;
+use std::{
+    borrow::Borrow,
+    ffi::{OsStr, OsString},
+    fs,
+    ops::Deref,
+    path::{Component, Path, PathBuf},
+    str::FromStr,
+};
+
+use super::error::RenditError;
+
+// This function attempts to be ignorant about any problems.
+// It just tries to figure out the absolute location of a given file path, without touching the file system.
+// If the path doesn't exist, it assumes someone else will scream about it.
+// On failure, it just returns the original Path.
+#[inline]
+fn new_canonicalize_path_buf<P: AsRef<Path>>(path: P) -> PathBuf {
+    // Canonicalize seem to be having trouble on Windows with relative paths that include a backslash.
+    // This work around is meant to make sure that before Canonicalize encounters the given path,
+    // its backslashes will be replaced with regular ones so `canonicalize` will be able to handle it.
+    let path: PathBuf = if path.as_ref().has_root() {
+        path.as_ref().into()
+    } else {
+        (&*path.as_ref().to_slash_lossy()).into()
+    };
+
+    match fs::canonicalize(&path) {
+        Ok(abs_path) => abs_path,
+        // Possible failures of `fs::canonicalize`:
+        //  1. path does not exist.
+        //  2. A non-final component in path is not a directory.
+        Err(_) => normalize_missing_path(&path).unwrap_or(path),
+    }
+}
+
+// How many dangling symlinks are followed in a row, before giving up on a loop.
+const MAX_SYMLINKS: usize = 40;
+
+// Resolves a path that doesn't exist (yet) by canonicalizing its nearest existing ancestor,
+// then appending the rest of its components lexically (`missing/../file` is `file`).
+// Returns `None` if the current directory is unavailable for a relative path.
+fn normalize_missing_path(path: &Path) -> Option<PathBuf> {
+    resolve_missing_path(path, 0)
+}
+
+fn resolve_missing_path(path: &Path, symlinks: usize) -> Option<PathBuf> {
+    let path = if path.is_absolute() {
+        path.to_owned()
+    } else {
+        std::env::current_dir().ok()?.join(path)
+    };
+
+    let mut found = None;
+
+    for ancestor in path.ancestors() {
+        if let Ok(resolved) = fs::canonicalize(ancestor) {
+            found = Some((resolved, path.strip_prefix(ancestor).ok()?));
+            break;
+        }
+
+        // A dangling symlink leads to its target, wherever that is, as writing through it would.
+        let is_symlink = fs::symlink_metadata(ancestor).is_ok_and(|m| m.file_type().is_symlink());
+
+        if is_symlink {
+            if symlinks >= MAX_SYMLINKS {
+                return None;
+            }
+            let target = ancestor.parent()?.join(fs::read_link(ancestor).ok()?);
+            let resolved = resolve_missing_path(&target, symlinks + 1)?;
+            found = Some((resolved, path.strip_prefix(ancestor).ok()?));
+            break;
+        }
+    }
+
+    let (mut resolved, rest) = found.unwrap_or_else(|| (PathBuf::new(), path.as_path()));
+
+    // The resolved ancestor is free of symlinks, and the missing components can't be any,
+    // so stepping out of them with `..` is the same as on disk.
+    for component in rest.components() {
+        match component {
+            Component::ParentDir => {
+                resolved.pop();
+            }
+            Component::CurDir => {}
+            component => resolved.push(component),
+        }
+    }
+    Some(resolved)
+}
+
+// Has the potential to be more correct. For the alpha and beta stages, I'll keep this function around.
+// #[inline]
+// fn new_full_path_buf<P: AsRef<Path>>(path: P) -> Result<PathBuf> {
+//     if path.as_ref().has_root() {
+//         Ok(path.as_ref().to_owned())
+//     } else {
+//         let exe_dir = current_exe()
+//             .context("Unable to get current executable file location")?
+//             .parent()
+//             .context("Unable to get current executable directory")?
+//             .to_owned();
+
+//         Ok(exe_dir.join(path))
+//     }
+// }
+
+// TODO: Move to an external crate, improve and with some more ideas and publish on crates.io.
+// TODO: `AbsolutePath` features should be implemented on `PathBuf` directly with proper traits, to avoid duplicating and interswitching between the types, making it seamless.
+// Old Note: Should behave just like a `PathBuf` and therefore should have the same methods + New security features (Restrict trait?)
+/// A path that is resolved into its absolute form upon creation.
+///
+/// Symlinks are resolved as well, so [`AbsolutePath::restrict_to`] tells where the path really leads.
+#[derive(Clone, Debug)]
+pub struct AbsolutePath {
+    path: PathBuf,
+}
+
+impl AbsolutePath {
+    #[inline]
+    pub fn into_inner(self) -> PathBuf {
+        self.path
+    }
+
+    /// Fails with [`RenditError::OutsideRoot`] unless the path is `root` or within it.
+    /// `root` is expected to be absolute and free of symlinks, such as another `AbsolutePath`.
+    pub fn restrict_to<P: AsRef<Path>>(&self, root: P) -> Result<&Self, RenditError> {
+        if self.path.starts_with(root.as_ref()) {
+            Ok(self)
+        } else {
+            Err(RenditError::OutsideRoot {
+                path: self.path.clone(),
+                root: root.as_ref().to_path_buf(),
+            })
+        }
+    }
+}
+
+impl AsRef<Path> for AbsolutePath {
+    #[inline]
+    fn as_ref(&self) -> &Path {
+        self.path.as_ref()
+    }
+}
+
+impl<T: ?Sized + AsRef<OsStr>> From<&T> for AbsolutePath {
+    /// Converts a borrowed [`OsStr`] to a [`AbsolutePath`].
+    ///
+    /// Allocates a [`AbsolutePath`] and copies the data into it.
+    #[inline]
+    fn from(s: &T) -> AbsolutePath {
+        AbsolutePath {
+            // path: new_full_path_buf(s.as_ref()).unwrap_or_else(|_| s.into()),
+            path: new_canonicalize_path_buf(s.as_ref()),
+        }
+    }
+}
+
+impl From<OsString> for AbsolutePath {
+    #[inline]
+    fn from(s: OsString) -> Self {
+        AbsolutePath {
+            // path: new_full_path_buf(&s).unwrap_or_else(|_| s.into()),
+            path: new_canonicalize_path_buf(s),
+        }
+    }
+}
+
+impl From<PathBuf> for AbsolutePath {
+    #[inline]
+    fn from(s: PathBuf) -> Self {
+        AbsolutePath {
+            // path: new_full_path_buf(&s).unwrap_or(s),
+            path: new_canonicalize_path_buf(s),
+        }
+    }
+}
+
+impl FromStr for AbsolutePath {
+    type Err = std::convert::Infallible;
+
+    #[inline]
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let res = AbsolutePath {
+            // path: new_full_path_buf(s).unwrap_or_else(|_| s.into()),
+            path: new_canonicalize_path_buf(s),
+        };
+        Ok(res)
+    }
+}
+
+impl std::fmt::Display for AbsolutePath {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.path.to_string_lossy())
+    }
+}
+
+// impl AsRef<Path> for &AbsolutePath {
+//     #[inline]
+//     fn as_ref(&self) -> &Path {
+//         self.path.as_ref()
+//     }
+// }
+
+impl AsRef<PathBuf> for AbsolutePath {
+    #[inline]
+    fn as_ref(&self) -> &PathBuf {
+        &self.path
+    }
+}
+
+impl AsRef<OsStr> for AbsolutePath {
+    #[inline]
+    fn as_ref(&self) -> &OsStr {
+        self.path.as_ref()
+    }
+}
+
+impl Borrow<Path> for AbsolutePath {
+    #[inline]
+    fn borrow(&self) -> &Path {
+        self.path.borrow()
+    }
+}
+
+impl Deref for AbsolutePath {
+    type Target = Path;
+
+    #[inline]
+    fn deref(&self) -> &Path {
+        self.path.deref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renditlib::test_util::TempDir;
+
+    #[test]
+    fn existing_relative_path() {
+        let expected = Path::new(env!("CARGO_MANIFEST_DIR")).join("Cargo.toml");
+
+        assert_eq!(
+            AbsolutePath::from("Cargo.toml").into_inner(),
+            fs::canonicalize(expected).unwrap()
+        );
+    }
+
+    #[test]
+    fn missing_relative_path() {
+        let cwd = fs::canonicalize(std::env::current_dir().unwrap()).unwrap();
+        let path = AbsolutePath::from("missing-dir/missing.html").into_inner();
+
+        assert_eq!(path, cwd.join("missing-dir").join("missing.html"));
+        assert!(!cwd.join("missing-dir").exists());
+    }
+
+    #[test]
+    fn missing_file_in_existing_dir() {
+        let dir = TempDir::new("path-missing");
+        let path = AbsolutePath::from(dir.join("out.html")).into_inner();
+
+        assert_eq!(path, dir.join("out.html"));
+        assert!(!path.exists(), "the file must not be created");
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn parent_components() {
+        let dir = TempDir::new("path-parent");
+        fs::create_dir(dir.join("sub")).unwrap();
+
+        assert_eq!(
+            AbsolutePath::from(dir.join("sub/../out.html")).into_inner(),
+            dir.join("out.html")
+        );
+        assert_eq!(
+            AbsolutePath::from(dir.join("missing/./deeper/../../out.html")).into_inner(),
+            dir.join("out.html")
+        );
+        assert!(!dir.join("missing").exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinked_paths() {
+        let dir = TempDir::new("path-symlink");
+        fs::create_dir_all(dir.join("target/inner")).unwrap();
+        fs::write(dir.join("target/inner/file.txt"), "").unwrap();
+        std::os::unix::fs::symlink(dir.join("target/inner"), dir.join("link")).unwrap();
+
+        // An existing file behind the symlink.
+        assert_eq!(
+            AbsolutePath::from(dir.join("link/file.txt")).into_inner(),
+            dir.join("target/inner/file.txt")
+        );
+        // A missing file behind the symlink, and stepping out of its target directory.
+        assert_eq!(
+            AbsolutePath::from(dir.join("link/missing.txt")).into_inner(),
+            dir.join("target/inner/missing.txt")
+        );
+        assert_eq!(
+            AbsolutePath::from(dir.join("link/missing/../../out.txt")).into_inner(),
+            dir.join("target/out.txt")
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn dangling_symlinks() {
+        let dir = TempDir::new("path-dangling");
+        fs::create_dir_all(dir.join("root")).unwrap();
+        fs::create_dir_all(dir.join("outside")).unwrap();
+        std::os::unix::fs::symlink("../outside/pwned.html", dir.join("root/out.html")).unwrap();
+        std::os::unix::fs::symlink("missing-dir", dir.join("root/link")).unwrap();
+
+        let path = AbsolutePath::from(dir.join("root/out.html"));
+
+        assert_eq!(path.to_path_buf(), dir.join("outside/pwned.html"));
+        assert!(path.restrict_to(dir.join("root")).is_err());
+        assert_eq!(
+            AbsolutePath::from(dir.join("root/link/file.txt")).into_inner(),
+            dir.join("root/missing-dir/file.txt")
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlink_loops() {
+        let dir = TempDir::new("path-loop");
+        std::os::unix::fs::symlink("b", dir.join("a")).unwrap();
+        std::os::unix::fs::symlink("a", dir.join("b")).unwrap();
+
+        // Gives up on the loop rather than overflowing the stack.
+        let _ = AbsolutePath::from(dir.join("a/file.txt"));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn backslashes_in_relative_paths() {
+        let cwd = fs::canonicalize(std::env::current_dir().unwrap()).unwrap();
+
+        assert_eq!(
+            AbsolutePath::from(r"missing-dir\missing.html").into_inner(),
+            cwd.join("missing-dir").join("missing.html")
+        );
+    }
+}
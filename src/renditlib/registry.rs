@@ -0,0 +1,414 @@
+use regex::RegexBuilder;
+use std::{
+    ops::Range,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+#[cfg(feature = "engine-handlebars")]
+use super::engines::HandlebarsRenderer;
+#[cfg(feature = "engine-liquid")]
+use super::engines::LiquidRenderer;
+#[cfg(feature = "engine-minijinja")]
+use super::engines::MiniJinjaRenderer;
+#[cfg(feature = "engine-mjml")]
+use super::engines::MjmlRenderer;
+#[cfg(feature = "engine-mustache")]
+use super::engines::MustacheRenderer;
+#[cfg(feature = "engine-subst")]
+use super::engines::SubstRenderer;
+#[cfg(feature = "engine-tera")]
+use super::engines::TeraRenderer;
+use super::{
+    engines::{EngineInfo, NoneRenderer, Renderer, OPTIONAL_ENGINES},
+    error::RenditError,
+    template::{EngineName, Template, TemplateData},
+};
+
+/// How many lines at the top of a template are searched for the magic comment.
+pub const MAGIC_COMMENT_LINES: usize = 5;
+
+/// Matches a line that is a magic comment, in the comment syntax of the templated file.
+/// The `#!rendit` form is only recognized on the first line.
+/// The `#`, `//` and `--` forms only count for engines that are known, as they also read as headings or prose.
+const MAGIC_COMMENT_PATTERN: &str = r"(?x)
+    ^\s*(?:
+        <!--template\s+(?P<html>.+?)\s*-->
+        | (?:\#|//|--)\s*template:\s*(?P<line>.+?)
+        | \{\#\s*template:?\s+(?P<jinja>.+?)\s*\#\}
+        | \#!rendit\s+(?P<shebang>.+?)
+    )\s*$";
+
+/// A magic comment that selects the engine of a template, optionally followed by options for rendering it
+/// (`<!--template tera ext=html strict-->`).
+pub(crate) struct MagicComment<'a> {
+    /// The engine name, or a `+` separated chain of them.
+    pub engine: &'a str,
+    /// The options that follow the engine name, as written.
+    pub options: &'a str,
+    /// The byte range of the comment's line, along with its line break.
+    pub line: Range<usize>,
+}
+
+/// Finds the magic comment within the first [`MAGIC_COMMENT_LINES`] lines of `contents`.
+///
+/// A `# template: name` line only counts if `is_engine` is true for each engine of the name
+/// (a `# Template: Overview` heading isn't a magic comment).
+pub(crate) fn find_magic_comment(
+    contents: &str,
+    is_engine: impl Fn(&str) -> bool,
+) -> Option<MagicComment<'_>> {
+    let re = RegexBuilder::new(MAGIC_COMMENT_PATTERN)
+        .case_insensitive(true)
+        .build()
+        .expect("Bad regex pattern.");
+
+    let mut start = 0;
+
+    for (index, line) in contents
+        .split_inclusive('\n')
+        .take(MAGIC_COMMENT_LINES)
+        .enumerate()
+    {
+        // (body, whether it is a `#`, `//` or `--` line comment)
+        let body = re.captures(line).and_then(|cap| {
+            ["html", "jinja"]
+                .iter()
+                .find_map(|name| cap.name(name))
+                .or_else(|| cap.name("shebang").filter(|_| index == 0))
+                .map(|body| (body, false))
+                .or_else(|| cap.name("line").map(|body| (body, true)))
+        });
+
+        if let Some((body, is_line_comment)) = body {
+            let (engine, options) = body
+                .as_str()
+                .split_once(char::is_whitespace)
+                .unwrap_or((body.as_str(), ""));
+
+            let is_engine_name = engine
+                .chars()
+                .all(|c| c.is_alphanumeric() || c == '_' || c == '+');
+
+            let is_known = || engine.split('+').all(&is_engine);
+
+            if is_engine_name && (!is_line_comment || is_known()) {
+                return Some(MagicComment {
+                    engine,
+                    options: options.trim(),
+                    line: start..start + line.len(),
+                });
+            }
+        }
+        start += line.len();
+    }
+    None
+}
+
+/// The set of engines available for detection and rendering.
+///
+/// `EngineRegistry::default()` holds the built-in engines. Third-party engines can be added with
+/// [`EngineRegistry::register`], after which they take part in `--engine` lookup,
+/// file extension and magic comment detection, and output file naming.
+#[derive(Clone)]
+pub struct EngineRegistry {
+    engines: Vec<Rc<dyn Renderer>>,
+}
+
+impl EngineRegistry {
+    /// Creates a registry without any engines.
+    pub fn new() -> Self {
+        EngineRegistry {
+            engines: Vec::new(),
+        }
+    }
+
+    /// Adds an engine to the registry.
+    /// An engine registered with an already existing name replaces the previous one.
+    pub fn register<R: Renderer + 'static>(&mut self, renderer: R) -> &mut Self {
+        let name = renderer.info().name;
+        let renderer: Rc<dyn Renderer> = Rc::new(renderer);
+
+        match self.engines.iter_mut().find(|r| r.info().name == name) {
+            Some(existing) => *existing = renderer,
+            None => self.engines.push(renderer),
+        }
+        self
+    }
+
+    /// Iterates over the registered engines, in registration order.
+    pub fn iter(&self) -> impl Iterator<Item = &dyn Renderer> {
+        self.engines.iter().map(|r| r.as_ref())
+    }
+
+    /// Finds an engine by its name or one of its aliases.
+    pub fn get(&self, name: &str) -> Option<&dyn Renderer> {
+        self.iter().find(|r| r.info().is_named(name))
+    }
+
+    /// Finds an engine by its name or one of its aliases, or fails with [`RenditError::UnknownEngine`].
+    /// Fails with [`RenditError::EngineNotBuiltIn`] instead, when asking for a built-in engine that was left out of the build.
+    pub fn try_get(&self, name: &str) -> Result<&dyn Renderer, RenditError> {
+        self.get(name).ok_or_else(|| self.engine_not_found(name))
+    }
+
+    /// Finds the engines of a `+` separated chain (`tera+mjml`), each by its name or one of its aliases.
+    /// The template is rendered by each engine in turn, with the output of one being the template of the next.
+    pub fn try_get_chain(&self, chain: &str) -> Result<Vec<&dyn Renderer>, RenditError> {
+        chain.split('+').map(|name| self.try_get(name)).collect()
+    }
+
+    /// Joins the canonical names of a chain of engines (`tera+mjml`).
+    pub fn chain_name(chain: &[&dyn Renderer]) -> EngineName {
+        chain
+            .iter()
+            .map(|r| r.info().name)
+            .collect::<Vec<_>>()
+            .join("+")
+    }
+
+    /// Returns the error for a missing engine, selected by any of its name, alias, file extension or magic comment key.
+    pub fn engine_not_found(&self, key: &str) -> RenditError {
+        match self.missing_builtin(key) {
+            Some((info, feature)) => RenditError::EngineNotBuiltIn {
+                engine: info.name.to_owned(),
+                feature,
+            },
+            None => RenditError::UnknownEngine(key.to_owned()),
+        }
+    }
+
+    /// Finds a built-in engine which was left out of the build, and isn't replaced by a registered engine.
+    fn missing_builtin(&self, key: &str) -> Option<(EngineInfo, &'static str)> {
+        OPTIONAL_ENGINES
+            .iter()
+            .find(|(info, _)| info.is_selected_by(key) && self.get(info.name).is_none())
+            .copied()
+    }
+
+    /// Finds an engine by a template file extension.
+    pub fn by_extension(&self, extension: &str) -> Option<&dyn Renderer> {
+        let extension = extension.to_lowercase();
+        self.iter()
+            .find(|r| r.info().extensions.contains(&extension.as_str()))
+    }
+
+    /// Whether a magic comment key selects a registered engine, or a built-in one that was left out of the build.
+    pub(crate) fn is_magic_key(&self, key: &str) -> bool {
+        self.by_magic_key(key).is_some() || self.missing_builtin(key).is_some()
+    }
+
+    /// Finds an engine by a magic comment key.
+    pub fn by_magic_key(&self, key: &str) -> Option<&dyn Renderer> {
+        let key = key.to_lowercase();
+        self.iter()
+            .find(|r| r.info().magic_keys.contains(&key.as_str()))
+    }
+
+    /// Decides on the engine of the template by first inspecting the file extension (e.g. `.tera`, `.hbs` or `.liq`).
+    /// If no engine extension is provided then the first lines of the template are inspected for a magic comment, such as `<!--template engine_name-->`.
+    pub fn detect(&self, td: &TemplateData) -> Template {
+        // Checking for template file extension to determine the template engine.
+        if let Some(template_file) = &td.file_path {
+            if let Some(extension) = template_file.extension() {
+                let extension = extension.to_string_lossy();
+
+                if let Some(renderer) = self.by_extension(&extension) {
+                    let info = renderer.info();
+
+                    if !info.post_processor {
+                        return Template::Engine(info.name.to_owned(), td.contents.clone());
+                    }
+
+                    // The template engine, if any, is still chosen by the magic comment.
+                    return match self.detect_magic_comment(&td.contents) {
+                        Template::Engine(chain, contents)
+                            if chain.rsplit('+').next() == Some(info.name) =>
+                        {
+                            Template::Engine(chain, contents)
+                        }
+                        Template::Engine(chain, contents) => {
+                            Template::Engine(format!("{chain}+{}", info.name), contents)
+                        }
+                        Template::NoEngine(contents) => {
+                            Template::Engine(info.name.to_owned(), contents)
+                        }
+                        unknown => unknown,
+                    };
+                }
+
+                if let Some((info, _)) = self.missing_builtin(&extension) {
+                    return Template::Unknown(info.name.to_owned(), td.contents.clone());
+                }
+            }
+        }
+        // Scan template contents for the magic comment to return the proper Template kind.
+        self.detect_magic_comment(&td.contents)
+    }
+
+    /// Inspect the first lines of the contents for a magic comment, and return the appropriate `Template` variation for rendering.
+    ///
+    /// The comment may be written as `<!--template tera-->`, `# template: tera`, `// template: tera`, `-- template: tera`,
+    /// `{# template tera #}`, or as `#!rendit tera` on the first line. Its line is stripped from the contents.
+    /// Engines may be chained with `+` (`<!--template tera+mjml-->`).
+    pub fn detect_magic_comment(&self, contents: &str) -> Template {
+        let Some(magic_comment) = find_magic_comment(contents, |key| self.is_magic_key(key)) else {
+            return Template::NoEngine(Rc::new(contents.to_owned()));
+        };
+
+        let stripped = Rc::new(format!(
+            "{}{}",
+            &contents[..magic_comment.line.start],
+            &contents[magic_comment.line.end..]
+        ));
+
+        let engine = magic_comment.engine.to_lowercase();
+
+        log::debug!("Detected magic comment: `{engine}`");
+
+        let chain: Result<Vec<_>, _> = engine
+            .split('+')
+            .map(|key| self.by_magic_key(key).ok_or(key))
+            .collect();
+
+        match chain {
+            Ok(chain) => Template::Engine(Self::chain_name(&chain), stripped),
+            Err(key) => Template::Unknown(key.to_owned(), stripped),
+        }
+    }
+
+    /// Returns the default output path for a rendered template file.
+    ///
+    /// Engine extensions are dropped (`my_template.html.tera` -> `my_template.html`), or replaced
+    /// by the output extension of the engine (`email.mjml` -> `email.html`),
+    /// any other extension is prefixed with `rendered` (`my_template.html` -> `my_template.rendered.html`).
+    pub fn rendered_path<P: AsRef<Path>>(&self, input_path: P) -> PathBuf {
+        let file_extension = input_path.as_ref().extension();
+
+        match file_extension {
+            Some(os_path_ext) => {
+                let path_ext = os_path_ext.to_string_lossy().to_lowercase();
+
+                if let Some(renderer) = self.by_extension(&path_ext) {
+                    input_path
+                        .as_ref()
+                        .with_extension(renderer.info().output_extension.unwrap_or_default())
+                } else {
+                    let new_ext = format!("rendered.{path_ext}");
+                    input_path.as_ref().with_extension(new_ext)
+                }
+            }
+            None => input_path.as_ref().with_extension(String::from("rendered")),
+        }
+    }
+}
+
+impl Default for EngineRegistry {
+    /// Creates a registry with the built-in engines that were compiled in.
+    fn default() -> Self {
+        let mut registry = EngineRegistry::new();
+        #[cfg(feature = "engine-tera")]
+        registry.register(TeraRenderer);
+        #[cfg(feature = "engine-liquid")]
+        registry.register(LiquidRenderer);
+        #[cfg(feature = "engine-handlebars")]
+        registry.register(HandlebarsRenderer);
+        #[cfg(feature = "engine-minijinja")]
+        registry.register(MiniJinjaRenderer);
+        #[cfg(feature = "engine-mustache")]
+        registry.register(MustacheRenderer);
+        #[cfg(feature = "engine-subst")]
+        registry.register(SubstRenderer::default());
+        #[cfg(feature = "engine-mjml")]
+        registry.register(MjmlRenderer);
+        registry.register(NoneRenderer);
+        registry
+    }
+}
+
+impl std::fmt::Debug for EngineRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list()
+            .entries(self.iter().map(|r| r.info().name))
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn engine_of(contents: &str) -> Option<&str> {
+        find_magic_comment(contents, |_| true).map(|magic_comment| magic_comment.engine)
+    }
+
+    #[test]
+    fn magic_comment_syntaxes() {
+        assert_eq!(engine_of("<!--template tera-->\n"), Some("tera"));
+        assert_eq!(engine_of("  <!--template   tera  -->  \n"), Some("tera"));
+        assert_eq!(engine_of("# template: handlebars\n"), Some("handlebars"));
+        assert_eq!(engine_of("// template: liquid\n"), Some("liquid"));
+        assert_eq!(engine_of("-- template: minijinja\n"), Some("minijinja"));
+        assert_eq!(engine_of("{# template tera #}\n"), Some("tera"));
+        assert_eq!(engine_of("{# template: tera #}\n"), Some("tera"));
+        assert_eq!(engine_of("#!rendit mustache\n"), Some("mustache"));
+        assert_eq!(engine_of("<!--TEMPLATE Tera+MJML-->"), Some("Tera+MJML"));
+    }
+
+    #[test]
+    fn magic_comment_options_and_line() {
+        let contents = "<html>\r\n  <!--template tera ext=html strict-->\r\n<body>";
+        let magic_comment = find_magic_comment(contents, |_| true).unwrap();
+
+        assert_eq!(magic_comment.engine, "tera");
+        assert_eq!(magic_comment.options, "ext=html strict");
+        assert_eq!(
+            &contents[magic_comment.line],
+            "  <!--template tera ext=html strict-->\r\n"
+        );
+    }
+
+    #[test]
+    fn not_a_magic_comment() {
+        assert_eq!(engine_of("text <!--template tera-->\n"), None);
+        assert_eq!(engine_of("# template: ../layouts/base.html\n"), None);
+        assert_eq!(engine_of("# template tera\n"), None);
+        assert_eq!(engine_of("<!--template-->\n"), None);
+        // The shebang form only counts on the first line.
+        assert_eq!(engine_of("\n#!rendit tera\n"), None);
+    }
+
+    #[test]
+    fn magic_comment_within_the_first_lines() {
+        let after = |lines: usize| format!("{}<!--template tera-->\n", "line\n".repeat(lines));
+
+        assert_eq!(engine_of(&after(MAGIC_COMMENT_LINES - 1)), Some("tera"));
+        assert_eq!(engine_of(&after(MAGIC_COMMENT_LINES)), None);
+    }
+
+    #[test]
+    fn line_comments_of_unknown_engines_are_text() {
+        let registry = EngineRegistry::default();
+
+        for contents in [
+            "# Template: Overview\n\nSome text",
+            "-- template: v2 schema\nCREATE TABLE t ();",
+        ] {
+            assert!(find_magic_comment(contents, |key| registry.is_magic_key(key)).is_none());
+            assert!(
+                matches!(registry.detect_magic_comment(contents), Template::NoEngine(c) if *c == contents)
+            );
+        }
+
+        // Engines that were left out of the build are still known, to report them.
+        let empty = EngineRegistry::new();
+        assert!(matches!(
+            empty.detect_magic_comment("# template: tera\n"),
+            Template::Unknown(engine, _) if engine == "tera"
+        ));
+        assert!(matches!(
+            empty.detect_magic_comment("<!--template overview-->\n"),
+            Template::Unknown(engine, _) if engine == "overview"
+        ));
+    }
+}
@@ -0,0 +1,663 @@
+use serde_json::Value;
+use std::{borrow::Cow, path::PathBuf, rc::Rc};
+
+use super::{
+    context::{merge_values, ArrayMerge, ContextData},
+    engines::{CompiledTemplate, Renderer},
+    error::RenditError,
+    front_matter::FrontMatter,
+    guess::LOW_CONFIDENCE,
+    path::AbsolutePath,
+    registry::EngineRegistry,
+    template::{
+        Contents, DetectionMethod, RenderedTemplate, Template, TemplateData, TemplateExtension,
+    },
+};
+
+/// Options that affect how the underlying engine renders the template.
+#[derive(Clone, Debug, Default)]
+pub struct RenderOptions {
+    /// Treat the template as a file of the given extension. May affect escaping of special characters.
+    pub extension: TemplateExtension,
+    /// Fail on variables that are missing from the context (`Some(true)`), or render them as empty (`Some(false)`).
+    /// `None` keeps the default of each engine.
+    pub strict: Option<bool>,
+    /// Normalizes the line endings of the output, or keeps them as rendered.
+    pub line_endings: LineEndings,
+    /// Rejects the template, and the files it includes or that its engine loads, if they resolve outside of the directory.
+    pub root: Option<AbsolutePath>,
+    /// Directories to look for included templates and partials in, after the template's directory.
+    pub include_dirs: Vec<AbsolutePath>,
+}
+
+impl RenderOptions {
+    /// Fails with [`RenditError::OutsideRoot`] if `path` resolves outside of the root directory, if any.
+    pub fn restrict<P: Into<AbsolutePath>>(&self, path: P) -> Result<(), RenditError> {
+        match &self.root {
+            Some(root) => path.into().restrict_to(root).map(|_| ()),
+            None => Ok(()),
+        }
+    }
+
+    /// Returns the template's directory followed by the include directories, in the order they are searched.
+    /// The first directory that has a referenced template or partial wins.
+    pub fn template_dirs(&self, template_dir: PathBuf) -> Vec<PathBuf> {
+        std::iter::once(template_dir)
+            .chain(self.include_dirs.iter().map(|dir| dir.to_path_buf()))
+            .collect()
+    }
+}
+
+/// How the line endings of the rendered output are written.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LineEndings {
+    /// Keeps the line endings as rendered.
+    #[default]
+    Preserve,
+    /// Writes every line ending as `\n`.
+    Lf,
+    /// Writes every line ending as `\r\n`.
+    Crlf,
+}
+
+impl LineEndings {
+    /// Rewrites the line endings of `text`, unless they are preserved.
+    pub fn apply<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        let lf = || text.replace("\r\n", "\n");
+
+        match self {
+            LineEndings::Preserve => Cow::Borrowed(text),
+            LineEndings::Lf if text.contains("\r\n") => Cow::Owned(lf()),
+            LineEndings::Crlf if text.contains('\n') => Cow::Owned(lf().replace('\n', "\r\n")),
+            LineEndings::Lf | LineEndings::Crlf => Cow::Borrowed(text),
+        }
+    }
+}
+
+impl From<&str> for LineEndings {
+    fn from(value: &str) -> Self {
+        match value.to_lowercase().as_str() {
+            "lf" => LineEndings::Lf,
+            "crlf" => LineEndings::Crlf,
+            _ => LineEndings::Preserve,
+        }
+    }
+}
+
+/// Detects the engine of the template out of the `registry` and renders it with the given context.
+pub fn render(
+    registry: &EngineRegistry,
+    template_data: &TemplateData,
+    context_data: &ContextData,
+    engine_detection: DetectionMethod,
+    options: &RenderOptions,
+) -> Result<RenderedTemplate, RenditError> {
+    prepare(registry, template_data, engine_detection, options)?.render(context_data)
+}
+
+/// Detects the engine of the template out of the `registry` and parses it, to be rendered with many contexts.
+///
+/// The front matter of the template and the options of its magic comment, if any, select the engine, the extension
+/// and the strictness unless they are given, and its values are the defaults of every context the template is rendered with.
+/// Front matter is only looked for in templates whose engine is forced, or selected by their extension or magic comment.
+pub fn prepare<'a>(
+    registry: &'a EngineRegistry,
+    template_data: &TemplateData,
+    engine_detection: DetectionMethod,
+    options: &RenderOptions,
+) -> Result<PreparedTemplate<'a>, RenditError> {
+    // A leading `---` block of any other file, such as a Markdown document, is its own text.
+    let (front_matter, template_data) = match (&engine_detection, registry.detect(template_data)) {
+        (DetectionMethod::Force(_), _) | (_, Template::Engine(..) | Template::Unknown(..)) => {
+            template_data.split_front_matter(registry)?
+        }
+        (_, Template::NoEngine(_)) => {
+            log::debug!("No template engine selected, leaving any front matter in place");
+            (None, template_data.clone())
+        }
+    };
+    let front_matter = front_matter.unwrap_or_default();
+    let template_data = &template_data;
+
+    let engine_detection = match (engine_detection, front_matter.engine.clone()) {
+        (DetectionMethod::Auto | DetectionMethod::Guess, Some(engine)) => {
+            log::debug!("Front matter engine: `{engine}`");
+            DetectionMethod::Force(engine)
+        }
+        (engine_detection, _) => engine_detection,
+    };
+
+    let mut options = options.clone();
+    if let (TemplateExtension::Auto, Some(extension)) =
+        (&options.extension, front_matter.extension.clone())
+    {
+        log::debug!("Front matter extension: \"{extension}\"");
+        options.extension = TemplateExtension::Force(extension);
+    }
+    options.strict = options.strict.or(front_matter.strict);
+
+    // The templates and partials that the template includes are checked by each engine, as they are loaded.
+    if let Some(template_file) = &template_data.file_path {
+        options.restrict(template_file.clone())?;
+    }
+
+    let template = match engine_detection {
+        DetectionMethod::Auto => {
+            log::debug!("Detection method: Automatic");
+            registry.detect(template_data)
+        }
+        DetectionMethod::Guess => {
+            log::debug!("Detection method: Automatic, or else guessed");
+            match registry.detect(template_data) {
+                Template::NoEngine(contents) => guess_engine(registry, contents)?,
+                template => template,
+            }
+        }
+        DetectionMethod::Force(engine) => {
+            log::debug!("Detection method: Manual = `{engine}`");
+            let chain = registry.try_get_chain(&engine)?;
+
+            // A magic comment is left out of the output, even though it doesn't decide on the engine.
+            let contents = match registry.detect_magic_comment(&template_data.contents) {
+                Template::Engine(_, contents)
+                | Template::Unknown(_, contents)
+                | Template::NoEngine(contents) => contents,
+            };
+            Template::Engine(EngineRegistry::chain_name(&chain), contents)
+        }
+    };
+
+    log::debug!("Selected engine: `{}`", template.get_engine());
+
+    let (engine, contents) = match template {
+        Template::Engine(engine, contents) => (engine, contents),
+        Template::Unknown(engine, _) => return Err(registry.engine_not_found(&engine)),
+        Template::NoEngine(raw) => {
+            return Ok(PreparedTemplate {
+                compiled: None,
+                post_processors: Vec::new(),
+                template_data: TemplateData {
+                    contents: raw,
+                    file_path: template_data.file_path.clone(),
+                },
+                front_matter,
+                options,
+            })
+        }
+    };
+
+    let template_data = TemplateData {
+        contents,
+        file_path: template_data.file_path.clone(),
+    };
+
+    let chain = registry.try_get_chain(&engine)?;
+
+    // Only the first engine sees the template itself, the rest of the chain renders its output.
+    let (first, post_processors) = chain
+        .split_first()
+        .ok_or(RenditError::UnknownEngine(engine))?;
+
+    Ok(PreparedTemplate {
+        compiled: Some(first.compile(&template_data, &options)?),
+        post_processors: post_processors.to_vec(),
+        template_data,
+        front_matter,
+        options,
+    })
+}
+
+/// Guesses the engine of a template that neither its file extension nor a magic comment select.
+/// Fails if another engine is as likely, and warns if the guess is uncertain.
+fn guess_engine(registry: &EngineRegistry, contents: Contents) -> Result<Template, RenditError> {
+    let Some(guess) = registry.guess(&contents) else {
+        log::warn!("Unable to guess the engine of the template, no engine specific syntax found");
+        return Ok(Template::NoEngine(contents));
+    };
+
+    if guess.is_ambiguous() {
+        let candidates = std::iter::once(guess.engine.clone())
+            .chain(
+                guess
+                    .alternatives
+                    .iter()
+                    .filter(|(_, confidence)| *confidence >= guess.confidence)
+                    .map(|(engine, _)| engine.to_string()),
+            )
+            .collect();
+        return Err(RenditError::AmbiguousEngine(candidates));
+    }
+
+    let confidence = (guess.confidence * 100.0).round();
+
+    if guess.confidence < LOW_CONFIDENCE {
+        let alternatives = guess
+            .alternatives
+            .iter()
+            .map(|(engine, confidence)| format!("`{engine}` {:.0}%", confidence * 100.0))
+            .collect::<Vec<_>>()
+            .join(", ");
+        log::warn!(
+            "Guessed engine `{}` with a low confidence of {confidence}%, against {alternatives}",
+            guess.engine
+        );
+    } else {
+        log::info!(
+            "Guessed engine `{}` with a confidence of {confidence}%",
+            guess.engine
+        );
+    }
+
+    Ok(Template::Engine(guess.engine, contents))
+}
+
+/// A template that was parsed by its engine, as returned by [`prepare`].
+pub struct PreparedTemplate<'a> {
+    /// The first engine of the chain, or `None` to pass the template through.
+    compiled: Option<Box<dyn CompiledTemplate + 'a>>,
+    post_processors: Vec<&'a dyn Renderer>,
+    template_data: TemplateData,
+    front_matter: FrontMatter,
+    options: RenderOptions,
+}
+
+impl PreparedTemplate<'_> {
+    /// The front matter of the template, along with the options of its magic comment.
+    pub fn front_matter(&self) -> &FrontMatter {
+        &self.front_matter
+    }
+
+    /// Renders the template with the given context.
+    pub fn render(&self, context_data: &ContextData) -> Result<RenderedTemplate, RenditError> {
+        let Some(compiled) = &self.compiled else {
+            return Ok(self.finish(self.template_data.contents.clone()));
+        };
+
+        // The context is merged over the values of the front matter.
+        let context = match &self.front_matter.defaults {
+            Value::Object(defaults) if !defaults.is_empty() => {
+                let mut context = self.front_matter.defaults.clone();
+                merge_values(
+                    &mut context,
+                    context_data.context.clone(),
+                    ArrayMerge::Replace,
+                );
+                Cow::Owned(context)
+            }
+            _ => Cow::Borrowed(&context_data.context),
+        };
+
+        // Each engine of the chain renders the output of the previous one.
+        let mut template_data = TemplateData {
+            contents: Rc::new(compiled.render(&context)?),
+            file_path: self.template_data.file_path.clone(),
+        };
+
+        for renderer in &self.post_processors {
+            let rendered = renderer.render(&template_data, &context, &self.options)?;
+            template_data.contents = Rc::new(rendered);
+        }
+
+        Ok(self.finish(template_data.contents))
+    }
+
+    /// Applies the line endings option to the output.
+    fn finish(&self, contents: Contents) -> RenderedTemplate {
+        match self.options.line_endings.apply(&contents) {
+            Cow::Borrowed(_) => RenderedTemplate(contents),
+            Cow::Owned(normalized) => RenderedTemplate(Rc::new(normalized)),
+        }
+    }
+}
+
+/// Builds and renders a template in a single call.
+///
+/// ```no_run
+/// use rendit::renditlib::RenderBuilder;
+///
+/// let rendered = RenderBuilder::new()
+///     .template_path("my_template.html.tera")
+///     .context(serde_json::json!({ "name": "World" }))
+///     .render()?;
+///
+/// println!("{rendered}");
+/// # Ok::<(), rendit::renditlib::RenditError>(())
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct RenderBuilder {
+    source: Option<String>,
+    path: Option<AbsolutePath>,
+    context: ContextData,
+    engine: DetectionMethod,
+    options: RenderOptions,
+    registry: EngineRegistry,
+}
+
+impl RenderBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the template contents. Takes precedence over reading the template from `template_path`.
+    pub fn template_source<S: Into<String>>(mut self, source: S) -> Self {
+        self.source = Some(source.into());
+        self
+    }
+
+    /// Sets the template file path. The template is read from it, unless `template_source` is set.
+    pub fn template_path<P: Into<AbsolutePath>>(mut self, path: P) -> Self {
+        self.path = Some(path.into());
+        self
+    }
+
+    /// Sets the rendering context. Defaults to an empty JSON object.
+    pub fn context<C: Into<ContextData>>(mut self, context: C) -> Self {
+        self.context = context.into();
+        self
+    }
+
+    /// Forces the engine of the given name or alias, instead of detecting it automatically.
+    /// Engines may be chained with `+` (`tera+mjml`).
+    pub fn engine<E: Into<DetectionMethod>>(mut self, engine: E) -> Self {
+        self.engine = engine.into();
+        self
+    }
+
+    /// Uses the engines of `registry` instead of the built-in ones.
+    pub fn registry(mut self, registry: EngineRegistry) -> Self {
+        self.registry = registry;
+        self
+    }
+
+    /// Forces the underlying engine to treat the template as a file of the given extension.
+    pub fn extension<X: Into<TemplateExtension>>(mut self, extension: X) -> Self {
+        self.options.extension = extension.into();
+        self
+    }
+
+    /// Fails on variables that are missing from the context, or renders them as empty, in every engine that supports it.
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.options.strict = Some(strict);
+        self
+    }
+
+    /// Normalizes the line endings of the output to `\n` or `\r\n`. They are kept as rendered by default.
+    pub fn line_endings(mut self, line_endings: LineEndings) -> Self {
+        self.options.line_endings = line_endings;
+        self
+    }
+
+    /// Rejects the template and the files it includes if they resolve outside of `root`.
+    pub fn root<P: Into<AbsolutePath>>(mut self, root: P) -> Self {
+        self.options.root = Some(root.into());
+        self
+    }
+
+    /// Adds a directory to look for included templates and partials in, after the template's directory
+    /// and the directories added before it.
+    pub fn include_dir<P: Into<AbsolutePath>>(mut self, dir: P) -> Self {
+        self.options.include_dirs.push(dir.into());
+        self
+    }
+
+    pub fn render(self) -> Result<RenderedTemplate, RenditError> {
+        let template_data = match (self.source, self.path) {
+            (Some(source), path) => TemplateData {
+                contents: Rc::new(source),
+                file_path: path,
+            },
+            (None, Some(path)) => TemplateData::from_file(path)?,
+            (None, None) => return Err(RenditError::MissingTemplate),
+        };
+
+        render(
+            &self.registry,
+            &template_data,
+            &self.context,
+            self.engine,
+            &self.options,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renditlib::test_util::TempDir;
+    use std::{error::Error, fs};
+
+    #[test]
+    fn line_endings() {
+        let mixed = "a\r\nb\nc\rd\r\n";
+
+        assert_eq!(LineEndings::Lf.apply(mixed), "a\nb\nc\rd\n");
+        assert_eq!(LineEndings::Crlf.apply(mixed), "a\r\nb\r\nc\rd\r\n");
+        assert_eq!(LineEndings::Crlf.apply("a\r\n\r\n"), "a\r\n\r\n");
+        assert!(matches!(LineEndings::Preserve.apply(mixed), Cow::Borrowed(text) if text == mixed));
+
+        // A lone `\r` is not a line ending, and is kept as is.
+        assert!(matches!(
+            LineEndings::Lf.apply("a\rb"),
+            Cow::Borrowed("a\rb")
+        ));
+        assert!(matches!(
+            LineEndings::Crlf.apply("a\rb"),
+            Cow::Borrowed("a\rb")
+        ));
+    }
+
+    #[test]
+    #[cfg(feature = "engine-tera")]
+    fn front_matter_of_templates_only() {
+        const MARKDOWN: &str = "---\ntitle: Notes\n---\n# {{ title }}\n";
+        let dir = TempDir::new("render-front-matter");
+
+        let render = |file: &str, engine: DetectionMethod| {
+            RenderBuilder::new()
+                .template_source(MARKDOWN)
+                .template_path(dir.join(file))
+                .engine(engine)
+                .render()
+                .unwrap()
+                .to_string()
+        };
+
+        assert_eq!(render("notes.md", DetectionMethod::Auto), MARKDOWN);
+        assert_eq!(render("notes.md", "tera".into()), "# Notes\n");
+        assert_eq!(render("notes.tera", DetectionMethod::Auto), "# Notes\n");
+    }
+
+    #[test]
+    #[cfg(all(
+        feature = "engine-tera",
+        feature = "engine-minijinja",
+        feature = "engine-mustache"
+    ))]
+    fn options_over_front_matter_and_magic_comment() {
+        let render = |source: &str, builder: RenderBuilder| {
+            builder
+                .template_source(source)
+                .render()
+                .map(|rendered| rendered.to_string())
+        };
+
+        let strict = "<!--template minijinja strict-->\n{{ missing }}";
+        assert!(render(strict, RenderBuilder::new()).is_err());
+        assert_eq!(
+            render(strict, RenderBuilder::new().strict(false)).unwrap(),
+            ""
+        );
+
+        let lenient = RenderBuilder::new().strict(false).engine("tera");
+        assert!(matches!(
+            render("{{ missing }}", lenient),
+            Err(RenditError::LenientNotSupported("tera"))
+        ));
+
+        // Mustache renders `1 + 1` as a missing variable.
+        let engine = "<!--template mustache-->\n---\nengine: tera\n---\n{{ 1 + 1 }}";
+        assert_eq!(render(engine, RenderBuilder::new()).unwrap(), "2");
+        assert_eq!(
+            render(engine, RenderBuilder::new().engine("mustache")).unwrap(),
+            ""
+        );
+    }
+
+    /// Whether the error, or one of its sources, is [`RenditError::OutsideRoot`].
+    fn is_outside_root(error: &RenditError) -> bool {
+        let mut source: Option<&dyn Error> = Some(error);
+
+        while let Some(error) = source {
+            if error
+                .to_string()
+                .contains("is outside of the root directory")
+            {
+                return true;
+            }
+            source = error.source();
+        }
+        false
+    }
+
+    #[test]
+    fn restrict() {
+        let dir = TempDir::new("render-restrict");
+        fs::create_dir_all(dir.join("root/sub")).unwrap();
+
+        let mut options = RenderOptions::default();
+        assert!(options.restrict(dir.join("outside.html")).is_ok());
+
+        options.root = Some(dir.join("root").into());
+        assert!(options.restrict(dir.join("root/sub/t.html")).is_ok());
+        assert!(options.restrict(dir.join("root/sub/../t.html")).is_ok());
+        assert!(matches!(
+            options.restrict(dir.join("root/../outside.html")),
+            Err(RenditError::OutsideRoot { .. })
+        ));
+        assert!(matches!(
+            options.restrict(dir.join("rootless/t.html")),
+            Err(RenditError::OutsideRoot { .. })
+        ));
+    }
+
+    /// The engines, along with the file name of their partial `p`, and a template that includes it.
+    const PARTIALS: &[(&str, &str, &str)] = &[
+        #[cfg(feature = "engine-tera")]
+        ("tera", "p.html", r#"{% include "p.html" %}"#),
+        #[cfg(feature = "engine-minijinja")]
+        ("minijinja", "p.html", r#"{% include "p.html" %}"#),
+        #[cfg(feature = "engine-handlebars")]
+        ("handlebars", "p.hbs", "{{> p}}"),
+        #[cfg(feature = "engine-liquid")]
+        ("liquid", "p.liquid", "{% include 'p' %}"),
+        #[cfg(feature = "engine-mustache")]
+        ("mustache", "p.mustache", "{{> p}}"),
+    ];
+
+    /// Renders a template of `root` whose partial `name` is only found in the `outside` include directory.
+    fn render_with_outside_partial(
+        name: &str,
+        engine: &str,
+        source: &str,
+        root: bool,
+    ) -> Result<RenderedTemplate, RenditError> {
+        let dir = TempDir::new(&format!("render-root-{engine}-{root}"));
+        fs::create_dir_all(dir.join("root")).unwrap();
+        fs::create_dir_all(dir.join("outside")).unwrap();
+        fs::write(dir.join("outside").join(name), "partial").unwrap();
+
+        let mut builder = RenderBuilder::new()
+            .template_source(source)
+            .template_path(dir.join("root/t.html"))
+            .engine(engine)
+            .include_dir(dir.join("outside"));
+        if root {
+            builder = builder.root(dir.join("root"));
+        }
+        builder.render()
+    }
+
+    #[test]
+    fn root_rejects_outside_partials_of_every_engine() {
+        for (engine, name, source) in PARTIALS {
+            let rendered = render_with_outside_partial(name, engine, source, false);
+            assert_eq!(rendered.unwrap().as_str(), "partial", "{engine}");
+
+            let error = render_with_outside_partial(name, engine, source, true).expect_err(engine);
+            assert!(is_outside_root(&error), "{engine}: {error:?}");
+        }
+    }
+
+    #[test]
+    fn partial_names_stay_below_their_directories() {
+        for (engine, name, source) in PARTIALS {
+            let dir = TempDir::new(&format!("render-names-{engine}"));
+            fs::create_dir_all(dir.join("template")).unwrap();
+            fs::write(dir.join(name), "secret").unwrap();
+
+            let source = source
+                .replace(" p", " ../p")
+                .replace("\"p", "\"../p")
+                .replace("'p", "'../p");
+            let rendered = RenderBuilder::new()
+                .template_source(source.as_str())
+                .template_path(dir.join("template/t.html"))
+                .engine(*engine)
+                .render();
+
+            assert!(
+                rendered.map_or(true, |r| !r.as_str().contains("secret")),
+                "{engine}"
+            );
+        }
+    }
+
+    #[test]
+    fn root_ignores_unused_outside_files() {
+        for (engine, name, _) in PARTIALS {
+            let rendered = render_with_outside_partial(name, engine, "plain", true);
+            assert_eq!(rendered.unwrap().as_str(), "plain", "{engine}");
+        }
+    }
+
+    #[cfg(feature = "engine-tera")]
+    #[test]
+    fn root_rejects_outside_tera_parents_and_macros() {
+        for source in [
+            r#"{% extends "p.html" %}"#,
+            r#"{% import "p.html" as p %}{{ p::m() }}"#,
+        ] {
+            let error = render_with_outside_partial("p.html", "tera", source, true).unwrap_err();
+            assert!(is_outside_root(&error), "{source}: {error:?}");
+        }
+    }
+
+    #[test]
+    fn template_dir_then_include_dirs_in_order() {
+        for (engine, name, source) in PARTIALS {
+            let dir = TempDir::new(&format!("render-include-{engine}"));
+            for sub in ["template", "one", "two"] {
+                fs::create_dir_all(dir.join(sub)).unwrap();
+                fs::write(dir.join(sub).join(name), sub).unwrap();
+            }
+
+            let render = || {
+                RenderBuilder::new()
+                    .template_source(*source)
+                    .template_path(dir.join("template/t.html"))
+                    .engine(*engine)
+                    .include_dir(dir.join("one"))
+                    .include_dir(dir.join("two"))
+                    .render()
+                    .unwrap()
+                    .to_string()
+            };
+
+            assert_eq!(render(), "template", "{engine}");
+            fs::remove_file(dir.join("template").join(name)).unwrap();
+            assert_eq!(render(), "one", "{engine}");
+            fs::remove_file(dir.join("one").join(name)).unwrap();
+            assert_eq!(render(), "two", "{engine}");
+        }
+    }
+}
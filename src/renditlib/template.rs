@@ -0,0 +1,151 @@
+use std::{fs, rc::Rc};
+
+use super::{
+    error::RenditError, front_matter::FrontMatter, path::AbsolutePath, registry::EngineRegistry,
+};
+
+pub type Contents = Rc<String>;
+pub type EngineName = String;
+
+/// A template along with the engine that was detected for it.
+#[non_exhaustive]
+pub enum Template {
+    /// A template for the registered engine of the given name.
+    Engine(EngineName, Contents),
+    /// A template that asks for an engine which isn't registered.
+    Unknown(EngineName, Contents),
+    NoEngine(Contents),
+}
+
+impl Template {
+    pub fn get_engine(&self) -> &str {
+        match self {
+            Template::Engine(engine, _) => engine,
+            Template::Unknown(_, _) => "unknown",
+            Template::NoEngine(_) => "no_engine",
+        }
+    }
+}
+
+/// The raw template contents, along with the file it was loaded from (if any).
+#[derive(Clone, Debug)]
+pub struct TemplateData {
+    pub contents: Contents,
+    pub file_path: Option<AbsolutePath>,
+}
+
+impl TemplateData {
+    /// Loads the template contents from `path`.
+    pub fn from_file<P: Into<AbsolutePath>>(path: P) -> Result<Self, RenditError> {
+        let path = path.into();
+        let contents = fs::read_to_string(&path)
+            .map_err(|e| RenditError::TemplateFile(path.to_path_buf(), e))?;
+
+        Ok(TemplateData {
+            contents: Rc::new(contents),
+            file_path: Some(path),
+        })
+    }
+
+    /// Splits off the front matter of the template, if there is one. See [`FrontMatter`].
+    pub fn split_front_matter(
+        &self,
+        registry: &EngineRegistry,
+    ) -> Result<(Option<FrontMatter>, TemplateData), RenditError> {
+        match FrontMatter::split(&self.contents, registry)? {
+            Some((front_matter, contents)) => Ok((
+                Some(front_matter),
+                TemplateData {
+                    contents: Rc::new(contents),
+                    file_path: self.file_path.clone(),
+                },
+            )),
+            None => Ok((None, self.clone())),
+        }
+    }
+
+    /// Uses `contents` as the template, without an associated file.
+    pub fn from_source<S: Into<String>>(contents: S) -> Self {
+        TemplateData {
+            contents: Rc::new(contents.into()),
+            file_path: None,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct RenderedTemplate(pub Rc<String>);
+
+impl RenderedTemplate {
+    #[inline]
+    pub fn as_str(&self) -> &str {
+        self.0.as_str()
+    }
+}
+
+impl std::fmt::Display for RenderedTemplate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub enum DetectionMethod {
+    #[default]
+    Auto,
+    /// Force the engine of the given name or alias.
+    Force(EngineName),
+    /// Detect the engine automatically, and failing that, guess it out of the template's syntax (`auto-guess`).
+    Guess,
+}
+
+impl DetectionMethod {
+    /// The `--engine` value that selects [`DetectionMethod::Guess`].
+    pub const AUTO_GUESS: &'static str = "auto-guess";
+}
+
+impl From<&str> for DetectionMethod {
+    fn from(engine: &str) -> Self {
+        match engine {
+            DetectionMethod::AUTO_GUESS => DetectionMethod::Guess,
+            engine => DetectionMethod::Force(engine.to_owned()),
+        }
+    }
+}
+
+impl From<Option<String>> for DetectionMethod {
+    fn from(engine: Option<String>) -> Self {
+        match engine {
+            Some(engine) => engine.as_str().into(),
+            None => DetectionMethod::Auto,
+        }
+    }
+}
+
+impl From<Option<&String>> for DetectionMethod {
+    fn from(engine: Option<&String>) -> Self {
+        engine.cloned().into()
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub enum TemplateExtension {
+    #[default]
+    Auto,
+    Force(String),
+}
+
+impl From<&str> for TemplateExtension {
+    fn from(s: &str) -> Self {
+        TemplateExtension::Force(s.to_owned())
+    }
+}
+
+impl From<Option<&String>> for TemplateExtension {
+    fn from(s: Option<&String>) -> Self {
+        match s {
+            Some(ext) => TemplateExtension::Force(ext.to_owned()),
+            None => TemplateExtension::Auto,
+        }
+    }
+}
@@ -0,0 +1,56 @@
+//! Helpers shared by the integration tests.
+#![allow(dead_code)]
+
+use assert_cmd::Command;
+use std::{
+    fs,
+    ops::Deref,
+    path::{Path, PathBuf},
+};
+
+/// A fresh, canonical directory under Cargo's temporary directory for tests, removed when dropped.
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    /// Creates the directory at `name`, a relative path that must be unique among the tests.
+    pub fn new(name: &str) -> Self {
+        let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(name);
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        TempDir(fs::canonicalize(dir).unwrap())
+    }
+}
+
+impl Deref for TempDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl AsRef<Path> for TempDir {
+    fn as_ref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+/// The `rendit` binary, without backtraces in its errors.
+pub fn rendit() -> Command {
+    let mut cmd = Command::cargo_bin("rendit").unwrap();
+    cmd.env("RUST_BACKTRACE", "0");
+    cmd
+}
+
+/// The `rendit` binary, run in `dir`.
+pub fn rendit_in<P: AsRef<Path>>(dir: P) -> Command {
+    let mut cmd = rendit();
+    cmd.current_dir(dir);
+    cmd
+}
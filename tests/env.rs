@@ -0,0 +1,38 @@
+//! `--env-prefix` only reads the environment variables that start with the prefix.
+#![cfg(all(unix, feature = "engine-tera"))]
+
+mod common;
+
+use assert_cmd::Command;
+use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+fn rendit() -> Command {
+    let mut cmd = common::rendit();
+    cmd.env("RENDIT_NAME", "World")
+        .args(["--engine", "tera", "--env-prefix", "RENDIT_"])
+        .write_stdin("Hello {{ env.NAME }}!");
+    cmd
+}
+
+#[test]
+fn ignores_other_non_utf8_variables() {
+    rendit()
+        .env("OTHER", OsStr::from_bytes(b"\xff"))
+        .assert()
+        .success()
+        .stdout("Hello World!");
+}
+
+#[test]
+fn rejects_matching_non_utf8_variables() {
+    let output = rendit()
+        .env("RENDIT_BAD", OsStr::from_bytes(b"\xff"))
+        .assert()
+        .failure();
+    let stderr = String::from_utf8_lossy(&output.get_output().stderr);
+
+    assert!(
+        stderr.contains("Environment variable `RENDIT_BAD` is not valid UTF-8"),
+        "{stderr}"
+    );
+}
@@ -0,0 +1,94 @@
+//! Runs the core modules of the official [Mustache spec](https://github.com/mustache/spec)
+//! against the `mustache` engine. The fixtures live in `tests/mustache-spec`.
+#![cfg(feature = "engine-mustache")]
+
+mod common;
+
+use common::TempDir;
+use rendit::renditlib::RenderBuilder;
+use serde_json::Value;
+use std::{fs, path::PathBuf};
+
+fn run_spec(module: &str) {
+    let spec_file = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("mustache-spec")
+        .join(format!("{module}.json"));
+
+    let spec: Value = serde_json::from_str(&fs::read_to_string(&spec_file).unwrap()).unwrap();
+    let tests = spec["tests"].as_array().expect("Spec file without tests");
+
+    let mut failures = Vec::new();
+
+    for (i, test) in tests.iter().enumerate() {
+        let name = test["name"].as_str().unwrap();
+
+        // Partials are resolved from the template's directory, so every test gets its own.
+        let test_dir = TempDir::new(&format!("mustache-spec/{module}/{i}"));
+
+        if let Some(partials) = test["partials"].as_object() {
+            for (partial, source) in partials {
+                fs::write(
+                    test_dir.join(format!("{partial}.mustache")),
+                    source.as_str().unwrap(),
+                )
+                .unwrap();
+            }
+        }
+
+        let rendered = RenderBuilder::new()
+            .template_source(test["template"].as_str().unwrap())
+            .template_path(test_dir.join("template.mustache"))
+            .context(test["data"].clone())
+            .render();
+
+        let expected = test["expected"].as_str().unwrap();
+
+        match rendered {
+            Ok(rendered) if rendered.as_str() == expected => {}
+            Ok(rendered) => failures.push(format!(
+                "{name}: expected {expected:?}, got {:?}",
+                rendered.as_str()
+            )),
+            Err(e) => failures.push(format!("{name}: {e:?}")),
+        }
+    }
+
+    assert!(
+        failures.is_empty(),
+        "{} of {} `{module}` spec tests failed:\n{}",
+        failures.len(),
+        tests.len(),
+        failures.join("\n")
+    );
+}
+
+#[test]
+fn comments() {
+    run_spec("comments");
+}
+
+#[test]
+fn delimiters() {
+    run_spec("delimiters");
+}
+
+#[test]
+fn interpolation() {
+    run_spec("interpolation");
+}
+
+#[test]
+fn inverted() {
+    run_spec("inverted");
+}
+
+#[test]
+fn partials() {
+    run_spec("partials");
+}
+
+#[test]
+fn sections() {
+    run_spec("sections");
+}
@@ -0,0 +1,64 @@
+//! `--ndjson` renders a template for every line of STDIN, reporting the lines that fail without stopping.
+#![cfg(feature = "engine-tera")]
+
+mod common;
+
+use assert_cmd::Command;
+use common::{rendit_in, TempDir};
+use serde_json::{json, Value};
+use std::{fs, path::Path};
+
+/// Lines that render, along with a malformed one, a blank one and one that misses a variable.
+const LINES: &str = "{\"name\": \"a\"}\nnot json\n\n{\"other\": 1}\n{\"name\": \"b\"}\n";
+
+/// A fresh directory holding the template `t.txt.tera`.
+fn sandbox(name: &str) -> TempDir {
+    let dir = TempDir::new(&format!("ndjson/{name}"));
+    fs::write(dir.join("t.txt.tera"), "Hi {{ name }}").unwrap();
+    dir
+}
+
+fn rendit(dir: &Path) -> Command {
+    let mut cmd = rendit_in(dir);
+    cmd.args(["t.txt.tera", "--ndjson"]).write_stdin(LINES);
+    cmd
+}
+
+#[test]
+fn reports_failed_lines() {
+    let dir = sandbox("lines");
+    let output = rendit(&dir).assert().failure();
+    let output = output.get_output();
+
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "Hi a\nHi b\n");
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Line 2: Invalid JSON context"), "{stderr}");
+    assert!(stderr.contains("Line 4: "), "{stderr}");
+    assert!(
+        stderr.contains("2 of the NDJSON lines failed to render"),
+        "{stderr}"
+    );
+}
+
+#[test]
+fn writes_error_records() {
+    let dir = sandbox("records");
+    let output = rendit(&dir).arg("--ndjson-output").assert().failure();
+
+    let records: Vec<Value> = String::from_utf8_lossy(&output.get_output().stdout)
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+
+    assert_eq!(records.len(), 4);
+    assert_eq!(records[0], json!({ "output": "Hi a" }));
+    assert_eq!(records[1]["line"], 2);
+    assert!(records[1]["error"]
+        .as_str()
+        .unwrap()
+        .starts_with("Invalid JSON context"));
+    assert_eq!(records[2]["line"], 4);
+    assert!(records[2]["error"].is_string());
+    assert_eq!(records[3], json!({ "output": "Hi b" }));
+}
@@ -0,0 +1,42 @@
+//! Command-line options take precedence over the front matter and magic comment options of a template.
+#![cfg(feature = "engine-tera")]
+
+mod common;
+
+use common::{rendit_in as rendit, TempDir};
+use std::fs;
+
+/// A fresh directory holding `t.html`, which asks for `magic.html` and `magic.json`.
+fn sandbox(name: &str) -> TempDir {
+    let dir = TempDir::new(&format!("options/{name}"));
+    fs::write(
+        dir.join("t.html"),
+        "<!--template tera output=magic.html context=magic.json-->\n{{ source }}",
+    )
+    .unwrap();
+    fs::write(dir.join("magic.json"), r#"{ "source": "magic" }"#).unwrap();
+    fs::write(dir.join("cli.json"), r#"{ "source": "cli" }"#).unwrap();
+    dir
+}
+
+#[test]
+fn magic_comment_options() {
+    let dir = sandbox("magic");
+
+    rendit(&dir).arg("t.html").assert().success();
+
+    assert_eq!(fs::read_to_string(dir.join("magic.html")).unwrap(), "magic");
+}
+
+#[test]
+fn cli_options_over_magic_comment_options() {
+    let dir = sandbox("cli");
+
+    rendit(&dir)
+        .args(["t.html", "--output", "cli.html", "--context", "cli.json"])
+        .assert()
+        .success();
+
+    assert_eq!(fs::read_to_string(dir.join("cli.html")).unwrap(), "cli");
+    assert!(!dir.join("magic.html").exists());
+}
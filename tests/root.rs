@@ -0,0 +1,142 @@
+//! `--root` rejects the files that resolve outside of the root directory.
+#![cfg(all(unix, feature = "engine-handlebars"))]
+
+mod common;
+
+use assert_cmd::Command;
+use common::{rendit_in, TempDir};
+use std::{fs, os::unix::fs::symlink, path::Path};
+
+/// A fresh directory with a `root` to render in, along with an empty context, and an `outside` next to it.
+fn sandbox(name: &str) -> TempDir {
+    let dir = TempDir::new(&format!("root/{name}"));
+    fs::create_dir_all(dir.join("root")).unwrap();
+    fs::create_dir_all(dir.join("outside")).unwrap();
+    fs::write(dir.join("root/default.ctx.json"), "{}").unwrap();
+    dir
+}
+
+trait AssertOutsideRoot {
+    fn assert_outside_root(&mut self);
+}
+
+impl AssertOutsideRoot for Command {
+    fn assert_outside_root(&mut self) {
+        let output = self.assert().failure();
+        let stderr = String::from_utf8_lossy(&output.get_output().stderr);
+
+        assert!(
+            stderr.contains("is outside of the root directory"),
+            "unexpected error: {stderr}"
+        );
+    }
+}
+
+fn rendit(dir: &Path) -> Command {
+    rendit_in(dir.join("root"))
+}
+
+#[test]
+fn rejects_dangling_symlink_as_default_output() {
+    let dir = sandbox("default-output");
+    fs::write(dir.join("root/t.html"), "pwned").unwrap();
+    symlink("../outside/pwned.html", dir.join("root/t.rendered.html")).unwrap();
+
+    rendit(&dir)
+        .args(["t.html", "--root", "."])
+        .assert_outside_root();
+
+    assert!(!dir.join("outside/pwned.html").exists());
+}
+
+#[test]
+fn rejects_dangling_symlink_as_output() {
+    let dir = sandbox("output");
+    fs::write(dir.join("root/t.html"), "pwned").unwrap();
+    symlink("../outside/pwned.html", dir.join("root/out.html")).unwrap();
+
+    rendit(&dir)
+        .args(["t.html", "--root", ".", "--output", "out.html"])
+        .assert_outside_root();
+
+    assert!(!dir.join("outside/pwned.html").exists());
+}
+
+#[test]
+fn rejects_dangling_symlink_as_front_matter_output() {
+    let dir = sandbox("front-matter-output");
+    fs::write(
+        dir.join("root/t.html"),
+        "<!--template handlebars-->\n---\noutput: out.html\n---\npwned",
+    )
+    .unwrap();
+    symlink("../outside/pwned.html", dir.join("root/out.html")).unwrap();
+
+    rendit(&dir)
+        .args(["t.html", "--root", "."])
+        .assert_outside_root();
+
+    assert!(!dir.join("outside/pwned.html").exists());
+}
+
+#[test]
+fn rejects_dangling_symlink_as_each_output() {
+    let dir = sandbox("each-output");
+    fs::write(dir.join("root/t.hbs"), "{{ id }}").unwrap();
+    fs::write(dir.join("root/rows.csv"), "id\na\n").unwrap();
+    symlink("../outside", dir.join("root/out")).unwrap();
+    fs::remove_dir(dir.join("outside")).unwrap();
+
+    rendit(&dir)
+        .args(["t.hbs", "--root", ".", "-c", "rows.csv", "--each"])
+        .args(["--output", "out/{{ id }}.txt"])
+        .assert_outside_root();
+
+    assert!(!dir.join("outside").exists());
+}
+
+#[test]
+fn rejects_template_outside() {
+    let dir = sandbox("template");
+    fs::write(dir.join("outside/t.hbs"), "pwned").unwrap();
+
+    rendit(&dir)
+        .args(["../outside/t.hbs", "--root", ".", "--stdout"])
+        .assert_outside_root();
+}
+
+#[test]
+fn rejects_symlinked_partial() {
+    let dir = sandbox("partial");
+    fs::write(dir.join("root/t.hbs"), "{{> p}}").unwrap();
+    fs::write(dir.join("outside/p.hbs"), "secret").unwrap();
+    symlink("../outside/p.hbs", dir.join("root/p.hbs")).unwrap();
+
+    rendit(&dir)
+        .args(["t.hbs", "--root", ".", "--stdout"])
+        .assert_outside_root();
+
+    rendit(&dir)
+        .args(["t.hbs", "--stdout"])
+        .assert()
+        .success()
+        .stdout("secret");
+}
+
+#[test]
+fn rejects_context_outside() {
+    let dir = sandbox("context");
+    fs::write(dir.join("root/t.hbs"), "{{ secret }}").unwrap();
+    fs::write(dir.join("outside/secret.json"), r#"{ "secret": 1 }"#).unwrap();
+
+    rendit(&dir)
+        .args([
+            "t.hbs",
+            "--root",
+            ".",
+            "--stdout",
+            "-c",
+            "../outside/secret.json",
+        ])
+        .assert_outside_root();
+}